@@ -6,7 +6,11 @@
 //! fn(&ExtendedLogRecord) -> bool
 //! ```
 
+use log::LogLevel;
 use rustc_serialize::json::{self, as_pretty_json};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 use ExtendedLogRecord;
 
 ///
@@ -27,7 +31,7 @@ use ExtendedLogRecord;
 /// ```
 /// # Result
 /// ```
-/// ExtendedLogRecord { level: "INFO", levelno: 3, msg: "test", target: "TestFactory", timestamp: 1493048120, module: "log_handlers::tests", file: "src/tests.rs", line: 15, date: "2017-04-24T15:35:20Z" }
+/// ExtendedLogRecord { level: "INFO", levelno: 3, msg: "test", target: "TestFactory", timestamp: 1493048120, module: "log_handlers::tests", file: "src/tests.rs", line: 15, date: "2017-04-24T15:35:20Z", sample_rate: None }
 /// ```
 pub fn default(record: &ExtendedLogRecord) -> String {
     format!("{:?}\n", record)
@@ -51,7 +55,7 @@ pub fn default(record: &ExtendedLogRecord) -> String {
 /// ```
 /// # Result
 /// ```json
-/// {"level":"INFO","levelno":3,"msg":"test","target":"TestFactory","timestamp":1493048347,"module":"log_handlers::tests","file":"src/tests.rs","line":15,"date":"2017-04-24T15:39:07Z"}
+/// {"level":"INFO","levelno":3,"msg":"test","target":"TestFactory","timestamp":1493048347,"module":"log_handlers::tests","file":"src/tests.rs","line":15,"date":"2017-04-24T15:39:07Z","sample_rate":null}
 /// ```
 pub fn json(record: &ExtendedLogRecord) -> String {
     format!("{}\n", json::encode(&record).unwrap())
@@ -89,4 +93,388 @@ pub fn json(record: &ExtendedLogRecord) -> String {
 /// ```
 pub fn pretty_json(record: &ExtendedLogRecord) -> String {
     format!("{}\n", as_pretty_json(&record))
-}
\ No newline at end of file
+}
+
+///
+/// Human readable formatter: date, level, target and message.
+///
+/// # Example
+///
+/// ```rust
+/// let rec = ExtendedLogRecord::new(
+///     file!(),
+///     LogLevel::Info,
+///     line!(),
+///     module_path!(),
+///     "test".to_string(),
+///     "TestFactory".to_string()
+/// );
+/// println!("{}", text(&rec));
+/// ```
+/// # Result
+/// ```
+/// 2017-04-24T15:45:29Z INFO  TestFactory: test
+/// ```
+pub fn text(record: &ExtendedLogRecord) -> String {
+    format!("{} {:<5} {}: {}\n", record.date, record.level, record.target, record.msg)
+}
+
+///
+/// Same as `text` with the level colored using ANSI escape codes.
+///
+/// # Example
+///
+/// ```rust
+/// println!("{}", colored(&rec));
+/// ```
+/// # Result
+/// ```
+/// 2017-04-24T15:45:29Z \x1b[32mINFO \x1b[0m TestFactory: test
+/// ```
+pub fn colored(record: &ExtendedLogRecord) -> String {
+    format!(
+        "{} {} {}: {}\n",
+        record.date,
+        colorize(record.level(), &format!("{:<5}", record.level)),
+        record.target,
+        record.msg
+    )
+}
+
+///
+/// Wrap `text` into the ANSI escape codes of the color associated to `level`.
+///
+/// Helper to write custom colored formatters.
+pub fn colorize(level: LogLevel, text: &str) -> String {
+    let color = match level {
+        LogLevel::Error => "1;31",
+        LogLevel::Warn => "33",
+        LogLevel::Info => "32",
+        LogLevel::Debug => "34",
+        LogLevel::Trace => "2",
+    };
+    format!("\x1b[{}m{}\x1b[0m", color, text)
+}
+
+///
+/// Format log record into logfmt: `key=value` pairs separated by spaces.
+///
+/// Values containing spaces, `=`, quotes or control characters are quoted, with quotes,
+/// backslashes and control characters escaped. `sample_rate` is appended to sampled records.
+///
+/// # Example
+///
+/// ```rust
+/// println!("{}", logfmt(&rec));
+/// ```
+/// # Result
+/// ```
+/// ts=2017-04-24T15:45:29Z level=info target=TestFactory msg="test done" file=src/tests.rs line=15
+/// ```
+pub fn logfmt(record: &ExtendedLogRecord) -> String {
+    let sample_rate = match record.sample_rate {
+        Some(rate) => format!(" sample_rate={}", rate),
+        None => String::new(),
+    };
+    format!(
+        "ts={} level={} target={} msg={} file={} line={}{}\n",
+        logfmt_value(&record.date),
+        logfmt_value(&record.level.to_lowercase()),
+        logfmt_value(&record.target),
+        logfmt_value(&record.msg),
+        logfmt_value(record.file),
+        record.line,
+        sample_rate
+    )
+}
+
+/// Quote and escape a logfmt value if needed.
+fn logfmt_value(value: &str) -> String {
+    let plain = !value.is_empty() && value.chars().all(|c| c != '"' && c != '=' && c != '\\' && !c.is_whitespace() && !c.is_control());
+    if plain {
+        return String::from(value);
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Field of a record in a `Pattern`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Date,
+    File,
+    Level,
+    Levelno,
+    Line,
+    Module,
+    Msg,
+    Target,
+    Timestamp,
+    SampleRate,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "date" => Some(Field::Date),
+            "file" => Some(Field::File),
+            "level" => Some(Field::Level),
+            "levelno" => Some(Field::Levelno),
+            "line" => Some(Field::Line),
+            "module" => Some(Field::Module),
+            "msg" => Some(Field::Msg),
+            "target" => Some(Field::Target),
+            "timestamp" => Some(Field::Timestamp),
+            "sample_rate" => Some(Field::SampleRate),
+            _ => None,
+        }
+    }
+
+    fn value(&self, record: &ExtendedLogRecord) -> String {
+        match *self {
+            Field::Date => record.date.clone(),
+            Field::File => String::from(record.file),
+            Field::Level => record.level.clone(),
+            Field::Levelno => record.levelno.to_string(),
+            Field::Line => record.line.to_string(),
+            Field::Module => String::from(record.module),
+            Field::Msg => record.msg.clone(),
+            Field::Target => record.target.clone(),
+            Field::Timestamp => record.timestamp.to_string(),
+            Field::SampleRate => record.sample_rate.map(|rate| rate.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Part of a compiled `Pattern`.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field {
+        field: Field,
+        fill: char,
+        align: char,
+        width: usize,
+        max: Option<usize>,
+    },
+    /// Rendered only if none of its fields is empty.
+    Optional(Vec<Segment>),
+}
+
+/// Error raised when a `Pattern` can't be compiled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternError {
+    /// Position of the error in the pattern, in characters.
+    pub position: usize,
+    /// Description of the error.
+    pub reason: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid pattern at {}: {}", self.position, self.reason)
+    }
+}
+
+impl Error for PatternError {
+    fn description(&self) -> &str {
+        &self.reason
+    }
+}
+
+///
+/// Template compiled from a pattern like `"{date} [{level:<5}] {target:.20}:{line} - {msg}"`.
+///
+/// Each `{field}` is replaced by a field of the record: `date`, `file`, `level`, `levelno`,
+/// `line`, `module`, `msg`, `target`, `timestamp` or `sample_rate` (empty unless the record was
+/// sampled). A field may be followed by a format spec
+/// `:[[fill]align][width][.max]`: the value is truncated to `max` characters, then padded to
+/// `width` characters with `fill` (a space by default), aligned to the left (`<`, the default),
+/// the right (`>`) or the center (`^`).
+///
+/// `{?...}` is an optional section, rendered only if none of its fields is empty, e.g.
+/// `{? ({file}:{line})}`. `{{` and `}}` are literal braces.
+///
+/// As formatters are plain functions, the pattern is compiled once in a static:
+///
+/// # Example
+///
+/// ```rust
+/// lazy_static! {
+///     static ref PATTERN: Pattern = Pattern::new("{date} [{level:<5}] {target:.20}:{line} - {msg}\n").unwrap();
+/// }
+///
+/// fn formatter(record: &ExtendedLogRecord) -> String {
+///     PATTERN.format(record)
+/// }
+///
+/// ExtendedLogger::add_stdout_handler(Some(LogLevelFilter::Info), Some(formatter));
+/// ```
+/// # Result
+/// ```
+/// 2017-04-24T15:45:29Z [INFO ] TestFactory:15 - test
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Compile a pattern.
+    pub fn new(pattern: &str) -> Result<Pattern, PatternError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        let segments = parse(&chars, &mut pos, false)?;
+        Ok(Pattern { segments })
+    }
+
+    /// Format a record.
+    pub fn format(&self, record: &ExtendedLogRecord) -> String {
+        let mut out = String::new();
+        render(&self.segments, record, &mut out);
+        out
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(pattern: &str) -> Result<Pattern, PatternError> {
+        Pattern::new(pattern)
+    }
+}
+
+/// Parse the segments from `pos` until the end of the pattern, or of the optional section.
+fn parse(chars: &[char], pos: &mut usize, nested: bool) -> Result<Vec<Segment>, PatternError> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    while *pos < chars.len() {
+        let start = *pos;
+        let next = chars.get(start + 1).cloned();
+        *pos += 1;
+        match (chars[start], next) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                literal.push(chars[start]);
+                *pos += 1;
+            }
+            ('{', Some('?')) => {
+                *pos += 1;
+                flush(&mut literal, &mut segments);
+                segments.push(Segment::Optional(parse(chars, pos, true)?));
+            }
+            ('{', _) => {
+                let end = match chars[start..].iter().position(|c| *c == '}') {
+                    Some(end) => start + end,
+                    None => return Err(error(start, "unclosed field")),
+                };
+                flush(&mut literal, &mut segments);
+                segments.push(parse_field(&chars[start + 1..end].iter().collect::<String>(), start)?);
+                *pos = end + 1;
+            }
+            ('}', _) if nested => {
+                flush(&mut literal, &mut segments);
+                return Ok(segments);
+            }
+            ('}', _) => return Err(error(start, "unmatched '}', use '}}' for a literal brace")),
+            (c, _) => literal.push(c),
+        }
+    }
+    if nested {
+        return Err(error(chars.len(), "unclosed optional section"));
+    }
+    flush(&mut literal, &mut segments);
+    Ok(segments)
+}
+
+/// Parse a field and its format spec, like `level:>5`.
+fn parse_field(field: &str, position: usize) -> Result<Segment, PatternError> {
+    let mut parts = field.splitn(2, ':');
+    let name = parts.next().unwrap_or("").trim();
+    let field = Field::from_name(name).ok_or_else(|| error(position, &format!("unknown field '{}'", name)))?;
+    let spec: Vec<char> = parts.next().unwrap_or("").chars().collect();
+    let is_align = |c: Option<&char>| c.map(|c| "<^>".contains(*c)).unwrap_or(false);
+    let (fill, align, mut idx) = if is_align(spec.get(1)) {
+        (spec[0], spec[1], 2)
+    } else if is_align(spec.first()) {
+        (' ', spec[0], 1)
+    } else {
+        (' ', '<', 0)
+    };
+    let number = |idx: &mut usize| {
+        let digits: String = spec[*idx..].iter().take_while(|c| c.is_ascii_digit()).collect();
+        *idx += digits.len();
+        digits.parse::<usize>().ok()
+    };
+    let width = number(&mut idx).unwrap_or(0);
+    let mut max = None;
+    if spec.get(idx) == Some(&'.') {
+        idx += 1;
+        max = number(&mut idx);
+        if max.is_none() {
+            return Err(error(position, "missing maximum width after '.'"));
+        }
+    }
+    if idx < spec.len() {
+        return Err(error(position, &format!("invalid format spec '{}'", spec.iter().collect::<String>())));
+    }
+    Ok(Segment::Field { field, fill, align, width, max })
+}
+
+/// Move the pending literal into the segments.
+fn flush(literal: &mut String, segments: &mut Vec<Segment>) {
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal.clone()));
+        literal.clear();
+    }
+}
+
+fn error(position: usize, reason: &str) -> PatternError {
+    PatternError { position, reason: String::from(reason) }
+}
+
+/// Render the segments, return `false` if a field is empty.
+fn render(segments: &[Segment], record: &ExtendedLogRecord, out: &mut String) -> bool {
+    let mut complete = true;
+    for segment in segments {
+        match *segment {
+            Segment::Literal(ref literal) => out.push_str(literal),
+            Segment::Field { field, fill, align, width, max } => {
+                let mut value = field.value(record);
+                if value.is_empty() {
+                    complete = false;
+                }
+                if let Some(max) = max {
+                    value = value.chars().take(max).collect();
+                }
+                let padding = width.saturating_sub(value.chars().count());
+                let (left, right) = match align {
+                    '>' => (padding, 0),
+                    '^' => (padding / 2, padding - padding / 2),
+                    _ => (0, padding),
+                };
+                out.extend((0..left).map(|_| fill));
+                out.push_str(&value);
+                out.extend((0..right).map(|_| fill));
+            }
+            Segment::Optional(ref segments) => {
+                let mut section = String::new();
+                if render(segments, record, &mut section) {
+                    out.push_str(&section);
+                }
+            }
+        }
+    }
+    complete
+}
@@ -0,0 +1,216 @@
+//!
+//! A handler to raise alerts when targets log too many records.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// Change of the state of a target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alert {
+    /// The target which crossed the threshold.
+    pub target: String,
+    /// Number of records in the window, at most `threshold + 1`.
+    pub count: usize,
+    /// `true` when the threshold is crossed, `false` when the target recovered.
+    pub firing: bool,
+}
+
+/// Destination of the alerts.
+enum Action {
+    /// The alerts are sent as records to a handler.
+    Record(Box<Handler>),
+    /// The alerts are passed to a callback.
+    Callback(Box<dyn FnMut(&Alert) + Send>),
+}
+
+/// Records of a target in the window.
+#[derive(Default)]
+struct Window {
+    /// When the last `threshold + 1` records were handled, oldest first.
+    times: VecDeque<Instant>,
+    /// Whether the threshold is crossed.
+    firing: bool,
+}
+
+impl Window {
+    /// Forget the records older than `duration`.
+    fn slide(&mut self, now: Instant, duration: Duration) {
+        while self.times.front().map(|time| now.duration_since(*time) >= duration).unwrap_or(false) {
+            self.times.pop_front();
+        }
+    }
+}
+
+/// Handler which counts the records of each target over a sliding window, and raises an alert
+/// when a target logs more than `threshold` records within `window`.
+///
+/// The handler level and filters select the counted records, usually the `ERROR` ones. A second
+/// alert follows when the target is back under the threshold. Alerts are either passed to a
+/// callback or sent as records to another handler: an `ERROR` record when the threshold is
+/// crossed and an `INFO` one on recovery.
+///
+/// Recoveries are checked whenever a record is handled, whatever its level, and by `check`.
+///
+/// # Examples
+///
+/// Alert when `db` logs more than 50 errors in a minute:
+///
+/// ```rust
+/// let mut hdlr = AlertHandler::with_callback(
+///     |alert| println!("{} {} errors: {}", alert.target, alert.count, alert.firing),
+///     50,
+///     Duration::from_secs(60),
+///     Some(LogLevelFilter::Error),
+/// );
+/// hdlr.filters.push(|record| record.target.starts_with("db"));
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct AlertHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Maximum number of records of a target within the window.
+    pub threshold: usize,
+    /// Duration of the sliding window.
+    pub window: Duration,
+    /// Destination of the alerts.
+    action: Action,
+    /// The windows by target.
+    windows: BTreeMap<String, Window>,
+    /// Earliest time a record leaves its window.
+    next_check: Option<Instant>,
+}
+
+impl AlertHandler {
+    /// Create a new handler instance which sends the alerts as records to `target`.
+    pub fn new(target: Handler, threshold: usize, window: Duration, level: Option<LogLevelFilter>) -> AlertHandler {
+        AlertHandler::with_action(Action::Record(Box::new(target)), threshold, window, level)
+    }
+
+    /// Create a new handler instance which passes the alerts to a callback.
+    ///
+    /// The callback is called by the logging thread, while the logger is locked: it must not log.
+    pub fn with_callback<F>(callback: F, threshold: usize, window: Duration, level: Option<LogLevelFilter>) -> AlertHandler
+        where F: FnMut(&Alert) + Send + 'static
+    {
+        AlertHandler::with_action(Action::Callback(Box::new(callback)), threshold, window, level)
+    }
+
+    fn with_action(action: Action, threshold: usize, window: Duration, level: Option<LogLevelFilter>) -> AlertHandler {
+        AlertHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            threshold,
+            window,
+            action,
+            windows: BTreeMap::new(),
+            next_check: None,
+        }
+    }
+
+    /// Targets which are currently over the threshold.
+    pub fn firing(&self) -> Vec<String> {
+        self.windows.iter().filter(|&(_, window)| window.firing).map(|(target, _)| target.clone()).collect()
+    }
+
+    /// Slide the windows and raise the alerts of the recovered targets.
+    ///
+    /// The windows are only scanned once a record is due to leave one of them.
+    pub fn check(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        if self.next_check.map(|at| now < at).unwrap_or(true) {
+            return Ok(());
+        }
+        let (threshold, duration) = (self.threshold, self.window);
+        let mut recovered = vec![];
+        for (target, window) in self.windows.iter_mut() {
+            window.slide(now, duration);
+            if window.firing && window.times.len() <= threshold {
+                window.firing = false;
+                recovered.push(Alert { target: target.clone(), count: window.times.len(), firing: false });
+            }
+        }
+        self.windows.retain(|_, window| !window.times.is_empty());
+        self.next_check = self.windows.values().filter_map(|window| window.times.front()).min().map(|time| *time + duration);
+        let mut result = Ok(());
+        for alert in recovered {
+            let raised = self.raise(&alert);
+            result = result.and(raised);
+        }
+        result
+    }
+
+    /// Send an alert to its destination.
+    fn raise(&mut self, alert: &Alert) -> io::Result<()> {
+        let (threshold, window) = (self.threshold, self.window.as_secs());
+        match self.action {
+            Action::Callback(ref mut callback) => {
+                callback(alert);
+                Ok(())
+            }
+            Action::Record(ref mut hdlr) => {
+                let (level, msg) = if alert.firing {
+                    (LogLevel::Error, format!(
+                        "alert: {} records from {} in {}s, above the threshold of {}",
+                        alert.count, alert.target, window, threshold
+                    ))
+                } else {
+                    (LogLevel::Info, format!(
+                        "recovered: {} records from {} in {}s, back under the threshold of {}",
+                        alert.count, alert.target, window, threshold
+                    ))
+                };
+                hdlr.handle(&ExtendedLogRecord::new(file!(), level, line!(), module_path!(), msg, String::from(module_path!())))
+            }
+        }
+    }
+}
+
+impl Filter for AlertHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for AlertHandler {
+    /// Check the recoveries, then if the log record may be emitted. `self.level` and filters
+    /// will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let checked = self.check();
+        if self.level >= record.level() && self.filter(record) {
+            checked.and(self.emit(record))
+        } else {
+            checked
+        }
+    }
+    /// Count the record in the window of its target, raising an alert if it crosses the threshold.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let (threshold, duration, now) = (self.threshold, self.window, Instant::now());
+        if self.next_check.is_none() {
+            self.next_check = Some(now + duration);
+        }
+        let alert = {
+            let window = self.windows.entry(record.target.clone()).or_default();
+            window.slide(now, duration);
+            window.times.push_back(now);
+            // Older records don't matter to tell whether the window is over the threshold.
+            if window.times.len() > threshold + 1 {
+                window.times.pop_front();
+            }
+            if window.firing || window.times.len() <= threshold {
+                return Ok(());
+            }
+            window.firing = true;
+            Alert { target: record.target.clone(), count: window.times.len(), firing: true }
+        };
+        self.raise(&alert)
+    }
+}
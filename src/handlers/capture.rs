@@ -0,0 +1,257 @@
+//!
+//! A handler and assertion macros to check the log records from tests.
+//!
+//! Records are captured per thread: each test starts its own `Capture` and only sees the
+//! records logged by its own thread, even when `cargo test` runs the tests in parallel.
+//!
+//! # Examples
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate log;
+//! #[macro_use]
+//! extern crate log_tools;
+//!
+//! use log::{LogLevel, LogLevelFilter};
+//! use log_tools::handlers::capture::{self, Capture};
+//!
+//! capture::init(LogLevelFilter::Debug).unwrap();
+//! let capture = Capture::start();
+//!
+//! warn!(target: "db", "query timeout after 30s");
+//!
+//! assert_logged!(level = Warn, target = "db", msg_contains = "timeout");
+//! assert_not_logged!(level = Error);
+//! assert_eq!(capture.count(LogLevel::Warn), 1);
+//! ```
+
+pub use log::LogLevel;
+
+use handlers::{Handle, Filter};
+use log::{LogLevelFilter, SetLoggerError};
+use std::cell::RefCell;
+use std::io;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use {ExtendedLogRecord, ExtendedLogger, OwnedLogRecord};
+
+thread_local! {
+    /// Records captured by the current thread, `None` when no capture is started.
+    static CAPTURED: RefCell<Option<Vec<OwnedLogRecord>>> = const { RefCell::new(None) };
+}
+
+static INIT: Once = Once::new();
+
+/// Set when the first call to `init` installed the logger.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Initialize the logger, if not done yet, and register a `CaptureHandler` accepting records up
+/// to `level`.
+///
+/// It may be called by every test, only the first call has an effect. Fails if another logger was
+/// installed before the first call: the records are then captured only if it is an
+/// `ExtendedLogger`.
+pub fn init(level: LogLevelFilter) -> Result<(), SetLoggerError> {
+    INIT.call_once(|| {
+        INSTALLED.store(ExtendedLogger::init(level).is_ok(), Ordering::SeqCst);
+        ExtendedLogger::add_capture_handler(Some(level));
+    });
+    if INSTALLED.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        // The logger is still set, this returns the error of the first call again.
+        ExtendedLogger::init(level)
+    }
+}
+
+/// Records captured by the current thread.
+///
+/// # Panics
+///
+/// Panics if no capture is started on the current thread.
+pub fn records() -> Vec<OwnedLogRecord> {
+    CAPTURED.with(|captured| {
+        captured.borrow().clone().expect("no capture started on this thread, see Capture::start")
+    })
+}
+
+/// Guard of the capture of the current thread.
+///
+/// Records are captured from `Capture::start` until the guard is dropped.
+pub struct Capture {
+    _private: (),
+}
+
+impl Capture {
+    /// Start capturing the records of the current thread, discarding previously captured ones.
+    pub fn start() -> Capture {
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(vec![]));
+        Capture { _private: () }
+    }
+
+    /// Records captured so far.
+    pub fn records(&self) -> Vec<OwnedLogRecord> {
+        records()
+    }
+
+    /// Number of records captured at `level`.
+    pub fn count(&self, level: LogLevel) -> usize {
+        self.records().iter().filter(|record| record.level() == level).count()
+    }
+
+    /// Number of records captured by level, from `Error` to `Trace`.
+    pub fn counts(&self) -> Vec<(LogLevel, usize)> {
+        [LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace]
+            .iter()
+            .map(|level| (*level, self.count(*level)))
+            .collect()
+    }
+
+    /// Discard the records captured so far.
+    pub fn clear(&self) {
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(vec![]));
+    }
+}
+
+impl Drop for Capture {
+    /// Stop capturing the records of the current thread.
+    fn drop(&mut self) {
+        CAPTURED.with(|captured| *captured.borrow_mut() = None);
+    }
+}
+
+/// Criteria used by `assert_logged!` and `assert_not_logged!`.
+#[derive(Debug, Default)]
+pub struct Query {
+    /// The record level.
+    pub level: Option<LogLevel>,
+    /// Prefix of the record target.
+    pub target: Option<String>,
+    /// Prefix of the record module.
+    pub module: Option<String>,
+    /// The whole record message.
+    pub msg: Option<String>,
+    /// Part of the record message.
+    pub msg_contains: Option<String>,
+}
+
+impl Query {
+    /// Determines if the record satisfies every criterion.
+    pub fn matches(&self, record: &OwnedLogRecord) -> bool {
+        self.level.map(|level| record.level() == level).unwrap_or(true)
+            && self.target.as_ref().map(|target| record.target.starts_with(target.as_str())).unwrap_or(true)
+            && self.module.as_ref().map(|module| record.module.starts_with(module.as_str())).unwrap_or(true)
+            && self.msg.as_ref().map(|msg| record.msg == *msg).unwrap_or(true)
+            && self.msg_contains.as_ref().map(|msg| record.msg.contains(msg.as_str())).unwrap_or(true)
+    }
+
+    /// Captured records which satisfy the query.
+    pub fn find(&self) -> Vec<OwnedLogRecord> {
+        records().into_iter().filter(|record| self.matches(record)).collect()
+    }
+}
+
+/// Assert that a record satisfying every criterion was captured on the current thread.
+///
+/// Criteria are `level` (a `LogLevel` variant name), `target` and `module` (prefixes), `msg`
+/// (the whole message) and `msg_contains`. Values other than literals must be parenthesized.
+///
+/// ```rust
+/// assert_logged!(level = Warn, target = "db", msg_contains = "timeout");
+/// ```
+#[macro_export]
+macro_rules! assert_logged {
+    ($($key:ident = $value:tt),+ $(,)*) => {{
+        let mut query = $crate::handlers::capture::Query::default();
+        $( $crate::__log_tools_query!(query, $key = $value); )+
+        if query.find().is_empty() {
+            panic!("no record matching {:?} was logged, captured records: {:#?}", query, $crate::handlers::capture::records());
+        }
+    }};
+}
+
+/// Assert that no record satisfying every criterion was captured on the current thread.
+///
+/// Accepts the same criteria as `assert_logged!`.
+///
+/// ```rust
+/// assert_not_logged!(level = Error, target = "db");
+/// ```
+#[macro_export]
+macro_rules! assert_not_logged {
+    ($($key:ident = $value:tt),+ $(,)*) => {{
+        let mut query = $crate::handlers::capture::Query::default();
+        $( $crate::__log_tools_query!(query, $key = $value); )+
+        let found = query.find();
+        if !found.is_empty() {
+            panic!("records matching {:?} were logged: {:#?}", query, found);
+        }
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_tools_query {
+    ($query:ident, level = $level:ident) => {
+        $query.level = Some($crate::handlers::capture::LogLevel::$level);
+    };
+    ($query:ident, target = $target:expr) => {
+        $query.target = Some(String::from($target));
+    };
+    ($query:ident, module = $module:expr) => {
+        $query.module = Some(String::from($module));
+    };
+    ($query:ident, msg = $msg:expr) => {
+        $query.msg = Some(String::from($msg));
+    };
+    ($query:ident, msg_contains = $msg:expr) => {
+        $query.msg_contains = Some(String::from($msg));
+    };
+}
+
+/// Handler which stores the records into the capture of the logging thread.
+///
+/// Records logged by a thread without a started `Capture` are ignored.
+pub struct CaptureHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+}
+
+impl CaptureHandler {
+    /// Create a new handler instance.
+    pub fn new(level: Option<LogLevelFilter>) -> CaptureHandler {
+        CaptureHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+        }
+    }
+}
+
+impl Filter for CaptureHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for CaptureHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Store a copy of the record if the current thread started a capture.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        CAPTURED.with(|captured| {
+            if let Some(ref mut records) = *captured.borrow_mut() {
+                records.push(OwnedLogRecord::from(record));
+            }
+        });
+        Ok(())
+    }
+}
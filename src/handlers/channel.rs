@@ -0,0 +1,99 @@
+//!
+//! A handler to deliver owned log records to the application.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::io;
+use std::sync::mpsc::Sender;
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// Destination of the records.
+enum Sink {
+    /// The records are sent into a channel.
+    Channel(Sender<OwnedLogRecord>),
+    /// The records are passed to a callback.
+    Callback(Box<dyn FnMut(OwnedLogRecord) + Send>),
+}
+
+/// Handler which delivers a copy of each record to the application, either into a channel or to
+/// a callback.
+///
+/// It lets the application display the records in its own UI or forward them over its own
+/// transport. Once the receiver is dropped, emitting a record fails with a `BrokenPipe` error.
+///
+/// # Examples
+///
+/// ```rust
+/// let (sender, receiver) = mpsc::channel();
+/// let mut hdlr = ChannelHandler::new(sender, Some(LogLevelFilter::Info));
+///
+/// hdlr.handle(&rec);
+///
+/// for record in receiver.try_iter() {
+///     println!("{}: {}", record.level, record.msg);
+/// }
+/// ```
+pub struct ChannelHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Destination of the records.
+    sink: Sink,
+}
+
+impl ChannelHandler {
+    /// Create a new handler instance which sends the records into a channel.
+    pub fn new(sender: Sender<OwnedLogRecord>, level: Option<LogLevelFilter>) -> ChannelHandler {
+        ChannelHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            sink: Sink::Channel(sender),
+        }
+    }
+
+    /// Create a new handler instance which passes the records to a callback.
+    ///
+    /// The callback is called by the logging thread, while the logger is locked: it must not log.
+    pub fn with_callback<F>(callback: F, level: Option<LogLevelFilter>) -> ChannelHandler
+        where F: FnMut(OwnedLogRecord) + Send + 'static
+    {
+        ChannelHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            sink: Sink::Callback(Box::new(callback)),
+        }
+    }
+}
+
+impl Filter for ChannelHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for ChannelHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Deliver a copy of the record.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let record = OwnedLogRecord::from(record);
+        match self.sink {
+            Sink::Channel(ref sender) => sender.send(record).map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "the receiver of the channel was dropped")
+            }),
+            Sink::Callback(ref mut callback) => {
+                callback(record);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//!
+//! A handler to plug user defined handlers into the logger.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::io;
+use ExtendedLogRecord;
+
+/// Handler which applies the level and filters of the built-in handlers before giving the records
+/// to a user defined `Handle` implementation.
+///
+/// The records are given to the user defined handler through its `handle`, so it may still
+/// apply its own checks.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut hdlr = CustomHandler::new(Box::new(MySink::new()), Some(LogLevelFilter::Warn));
+/// hdlr.filters.push(|record| record.target.starts_with("db"));
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct CustomHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The user defined handler.
+    pub handler: Box<dyn Handle + Send>,
+}
+
+impl CustomHandler {
+    /// Create a new handler instance wrapping `handler`.
+    pub fn new(handler: Box<dyn Handle + Send>, level: Option<LogLevelFilter>) -> CustomHandler {
+        CustomHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            handler,
+        }
+    }
+}
+
+impl Filter for CustomHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for CustomHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Give the record to the user defined handler.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        self.handler.handle(record)
+    }
+}
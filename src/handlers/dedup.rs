@@ -0,0 +1,169 @@
+//!
+//! A handler to suppress duplicate log records.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::LogLevelFilter;
+use std::io;
+use std::time::{Duration, Instant};
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// How the `DedupHandler` determines that two records are duplicates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DedupKey {
+    /// Same level, target and message.
+    Message,
+    /// Same source file and line, whatever the message.
+    Location,
+}
+
+/// Identity of a record according to a `DedupKey`.
+#[derive(Clone, Debug, PartialEq)]
+enum Key {
+    Message(u32, String, String),
+    Location(String, u32),
+}
+
+impl Key {
+    fn new(key: DedupKey, record: &ExtendedLogRecord) -> Key {
+        match key {
+            DedupKey::Message => Key::Message(record.levelno, record.target.clone(), record.msg.clone()),
+            DedupKey::Location => Key::Location(String::from(record.file), record.line),
+        }
+    }
+}
+
+/// A record sent to the target handler and its suppressed duplicates.
+struct Entry {
+    /// The last suppressed duplicate, or the emitted record.
+    record: OwnedLogRecord,
+    /// When the record was emitted.
+    since: Instant,
+    /// Number of suppressed duplicates.
+    repeats: u64,
+}
+
+/// Handler which sends the records to `target`, suppressing the duplicates.
+///
+/// Without `window`, a record is a duplicate of the previous record only. With a `window`, a
+/// record is a duplicate of any record emitted less than `window` ago, which catches duplicates
+/// interleaved with other records; once the window is over, the record is emitted again.
+///
+/// The suppressed duplicates are reported by a single `message repeated N time(s): <msg>` record,
+/// with the level, target and location of the last duplicate. It is sent when a different record
+/// is logged or the window is over, and when the handler is dropped.
+///
+/// # Examples
+///
+/// Collapse the retry loops logging the same line:
+///
+/// ```rust
+/// let mut hdlr = DedupHandler::new(
+///     Handler::from(StdoutHandler::new(Some(LogLevelFilter::Info), None)),
+///     DedupKey::Location,
+///     Some(Duration::from_secs(60)),
+///     Some(LogLevelFilter::Info),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct DedupHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The handler which receives the records and the summaries.
+    pub target: Box<Handler>,
+    /// How duplicates are detected.
+    pub key: DedupKey,
+    /// Period during which duplicates are suppressed, `None` to only suppress consecutive ones.
+    pub window: Option<Duration>,
+    /// The records emitted recently, or only the previous one without window, oldest first.
+    entries: Vec<(Key, Entry)>,
+}
+
+impl DedupHandler {
+    /// Create a new handler instance.
+    pub fn new(target: Handler, key: DedupKey, window: Option<Duration>, level: Option<LogLevelFilter>) -> DedupHandler {
+        DedupHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            target: Box::new(target),
+            key,
+            window,
+            entries: vec![],
+        }
+    }
+
+    /// Forget the entries which don't match `key` or which window is over, sending their summary.
+    fn expire(&mut self, key: &Key) -> io::Result<()> {
+        let window = self.window;
+        let (expired, kept) = self.entries.drain(..).partition(|(other, entry)| match window {
+            Some(window) => entry.since.elapsed() >= window,
+            None => other != key,
+        });
+        self.entries = kept;
+        let mut result = Ok(());
+        for (_, entry) in expired {
+            let sent = summarize(&mut self.target, &entry);
+            result = result.and(sent);
+        }
+        result
+    }
+}
+
+/// Send the summary of the suppressed duplicates of an entry, if any.
+fn summarize(target: &mut Handler, entry: &Entry) -> io::Result<()> {
+    if entry.repeats == 0 {
+        return Ok(());
+    }
+    let record = &entry.record;
+    let times = if entry.repeats == 1 { "time" } else { "times" };
+    target.handle(&ExtendedLogRecord::new(
+        &record.file,
+        record.level(),
+        record.line,
+        &record.module,
+        format!("message repeated {} {}: {}", entry.repeats, times, record.msg),
+        record.target.clone(),
+    ))
+}
+
+impl Drop for DedupHandler {
+    /// Send the summaries of the pending duplicates.
+    fn drop(&mut self) {
+        for (_, entry) in self.entries.drain(..) {
+            let _ = summarize(&mut self.target, &entry);
+        }
+    }
+}
+
+impl Filter for DedupHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for DedupHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the record to the target handler unless it is a duplicate.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let key = Key::new(self.key, record);
+        let expired = self.expire(&key);
+        if let Some(&mut (_, ref mut entry)) = self.entries.iter_mut().find(|&&mut (ref other, _)| *other == key) {
+            entry.record = OwnedLogRecord::from(record);
+            entry.repeats += 1;
+            return expired;
+        }
+        self.entries.push((key, Entry { record: OwnedLogRecord::from(record), since: Instant::now(), repeats: 0 }));
+        expired.and(self.target.handle(record))
+    }
+}
@@ -0,0 +1,158 @@
+//!
+//! A handler to aggregate log records into periodic summaries.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// Template of a record message: the words containing a digit are replaced by `*`.
+///
+/// `"query 42 took 120ms"` and `"query 7 took 85ms"` share the template `"query * took *"`.
+pub fn template(record: &ExtendedLogRecord) -> String {
+    record.msg.split_whitespace()
+        .map(|word| if word.chars().any(|c| c.is_ascii_digit()) { "*" } else { word })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Records of a window sharing level, target and template.
+struct Bucket {
+    /// Number of records.
+    count: u64,
+    /// Date of the first record.
+    first: String,
+    /// Date of the last record.
+    last: String,
+    /// Message of the first record.
+    example: String,
+}
+
+/// Handler which aggregates the records over a window and sends a summary to `target`.
+///
+/// The records are grouped by level, target and message template (see `template`). When the
+/// window is over, a single record summarizing each group (count, first and last dates and an
+/// example message) is sent to the target handler, with the level of the most severe group:
+///
+/// ```text
+/// digest of 1523 records over 60s:
+///   1520 x WARN db "retrying query * after *" (2017-01-01T10:00:00Z - 2017-01-01T10:00:59Z), e.g. "retrying query 12 after 100ms"
+///   3 x INFO api "listening on *" (2017-01-01T10:00:01Z - 2017-01-01T10:00:03Z), e.g. "listening on 0.0.0.0:80"
+/// ```
+///
+/// The end of the window is checked when a record is handled, the pending summary is also sent
+/// by `flush` and when the handler is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut hdlr = DigestHandler::new(
+///     Handler::from(StdoutHandler::new(Some(LogLevelFilter::Trace), None)),
+///     Duration::from_secs(60),
+///     Some(LogLevelFilter::Info),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct DigestHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The handler which receives the summaries.
+    pub target: Box<Handler>,
+    /// Duration of a window.
+    pub window: Duration,
+    /// Callback returning the template of a record message.
+    pub template: fn(&ExtendedLogRecord) -> String,
+    /// Start of the current window.
+    started: Instant,
+    /// Groups of the current window, by level, target and template.
+    buckets: BTreeMap<(LogLevel, String, String), Bucket>,
+}
+
+impl DigestHandler {
+    /// Create a new handler instance.
+    pub fn new(target: Handler, window: Duration, level: Option<LogLevelFilter>) -> DigestHandler {
+        DigestHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            target: Box::new(target),
+            window,
+            template,
+            started: Instant::now(),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    /// Send the summary of the current window, if it has records, and start a new window.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let elapsed = self.started.elapsed();
+        self.started = Instant::now();
+        let level = match self.buckets.keys().next() {
+            Some(&(level, _, _)) => level,
+            None => return Ok(()),
+        };
+        let total: u64 = self.buckets.values().map(|bucket| bucket.count).sum();
+        let mut msg = format!("digest of {} records over {}s:", total, elapsed.as_secs());
+        for (&(level, ref target, ref template), bucket) in self.buckets.iter() {
+            let _ = write!(
+                msg,
+                "\n  {} x {} {} {:?} ({} - {}), e.g. {:?}",
+                bucket.count, level, target, template, bucket.first, bucket.last, bucket.example
+            );
+        }
+        self.buckets.clear();
+        self.target.handle(&ExtendedLogRecord::new(
+            file!(),
+            level,
+            line!(),
+            module_path!(),
+            msg,
+            String::from(module_path!()),
+        ))
+    }
+}
+
+impl Drop for DigestHandler {
+    /// Send the summary of the current window.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Filter for DigestHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for DigestHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Count the record in its group, sending the summary first if the window is over.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let flushed = if self.started.elapsed() >= self.window { self.flush() } else { Ok(()) };
+        let key = (record.level(), record.target.clone(), (self.template)(record));
+        let bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+            count: 0,
+            first: record.date.clone(),
+            last: String::new(),
+            example: record.msg.clone(),
+        });
+        bucket.count += 1;
+        bucket.last = record.date.clone();
+        flushed
+    }
+}
@@ -0,0 +1,400 @@
+//!
+//! A handler to extract metrics from the messages of the log records.
+//!
+
+use handlers::{Handle, Filter};
+use handlers::metrics::{escape, escape_help};
+use log::LogLevelFilter;
+use regex::{self, Regex};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::io;
+use std::sync::{Arc, Mutex};
+use ExtendedLogRecord;
+
+/// Type of the metric fed by a `MetricRule`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricKind {
+    /// Incremented by the captured value, or by 1 if the regex has no value group. Negative values
+    /// are skipped, as a counter never decreases.
+    Counter,
+    /// Set to the captured value.
+    Gauge,
+    /// Observes the captured value, with the given bucket upper bounds: finite and increasing.
+    Histogram(Vec<f64>),
+}
+
+/// Error raised when a `MetricRule` can't be created.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleError {
+    /// The regex doesn't compile.
+    Regex(regex::Error),
+    /// The name is not a valid Prometheus metric name.
+    MetricName(String),
+    /// The named group is not a valid Prometheus label name, or is reserved.
+    LabelName(String),
+    /// No group captures the value of a gauge or histogram.
+    NoValue,
+    /// The bucket bounds of the histogram are empty, not finite or not increasing.
+    Buckets(Vec<f64>),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RuleError::Regex(ref err) => write!(f, "invalid regex: {}", err),
+            RuleError::MetricName(ref name) => write!(f, "invalid metric name {}", name),
+            RuleError::LabelName(ref name) => write!(f, "invalid label name {}", name),
+            RuleError::NoValue => write!(f, "no group captures the value"),
+            RuleError::Buckets(ref bounds) => write!(f, "invalid bucket bounds {:?}", bounds),
+        }
+    }
+}
+
+impl Error for RuleError {
+    fn description(&self) -> &str {
+        match *self {
+            RuleError::Regex(_) => "invalid regex",
+            RuleError::MetricName(_) => "invalid metric name",
+            RuleError::LabelName(_) => "invalid label name",
+            RuleError::NoValue => "no group captures the value",
+            RuleError::Buckets(_) => "invalid bucket bounds",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RuleError::Regex(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<regex::Error> for RuleError {
+    fn from(err: regex::Error) -> RuleError {
+        RuleError::Regex(err)
+    }
+}
+
+/// Label names and values of a metric.
+type Labels = Vec<(String, String)>;
+
+/// Rule extracting a metric from the messages matching a regex.
+///
+/// The value is captured by the group named `value`, or else by the first unnamed group. The
+/// other named groups become labels of the metric: `(?P<table>\w+) query took
+/// (\d+)ms` feeds a metric per table.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut rule = MetricRule::new(
+///     "db_query_duration_ms",
+///     r"took (\d+)ms",
+///     MetricKind::Histogram(vec![10.0, 100.0, 1000.0]),
+/// ).unwrap();
+/// rule.target = Some(String::from("db"));
+/// ```
+pub struct MetricRule {
+    /// Name of the metric.
+    pub name: String,
+    /// Description of the metric.
+    pub help: String,
+    /// Type of the metric.
+    pub kind: MetricKind,
+    /// Only the records which target starts with this prefix are matched.
+    pub target: Option<String>,
+    /// Only the records up to this level are matched.
+    pub level: LogLevelFilter,
+    /// The regex matched against the messages.
+    regex: Regex,
+    /// Index of the group capturing the value.
+    value: Option<usize>,
+}
+
+impl MetricRule {
+    /// Create a new rule, the regex must have a value group unless the metric is a counter.
+    ///
+    /// `name` and the named groups must be valid Prometheus metric and label names, and a
+    /// histogram can't have a group named `le`, which is the label of its buckets.
+    pub fn new(name: &str, pattern: &str, kind: MetricKind) -> Result<MetricRule, RuleError> {
+        if !is_metric_name(name) {
+            return Err(RuleError::MetricName(String::from(name)));
+        }
+        if let MetricKind::Histogram(ref bounds) = kind {
+            let increasing = bounds.windows(2).all(|pair| pair[0] < pair[1]);
+            if bounds.is_empty() || !increasing || !bounds.iter().all(|bound| bound.is_finite()) {
+                return Err(RuleError::Buckets(bounds.clone()));
+            }
+        }
+        let regex = Regex::new(pattern)?;
+        let names: Vec<Option<&str>> = regex.capture_names().collect();
+        for label in names.iter().filter_map(|name| *name).filter(|name| *name != "value") {
+            let reserved = match kind {
+                MetricKind::Histogram(_) => label == "le",
+                _ => false,
+            };
+            if reserved || !is_label_name(label) {
+                return Err(RuleError::LabelName(String::from(label)));
+            }
+        }
+        let value = match names.iter().position(|name| *name == Some("value")) {
+            Some(idx) => Some(idx),
+            None => names.iter().skip(1).position(|name| name.is_none()).map(|idx| idx + 1),
+        };
+        if kind != MetricKind::Counter && value.is_none() {
+            return Err(RuleError::NoValue);
+        }
+        Ok(MetricRule {
+            name: String::from(name),
+            help: format!("Extracted from the log records matching {}.", pattern),
+            kind,
+            target: None,
+            level: LogLevelFilter::Trace,
+            regex,
+            value,
+        })
+    }
+
+    /// Labels and value extracted from a record, if it matches the rule.
+    ///
+    /// The value is `None` if the rule has no value group or the value is not a number.
+    fn extract(&self, record: &ExtendedLogRecord) -> Option<(Labels, Option<f64>)> {
+        if self.level < record.level() {
+            return None;
+        }
+        if let Some(ref target) = self.target {
+            if !record.target.starts_with(target.as_str()) {
+                return None;
+            }
+        }
+        let captures = self.regex.captures(&record.msg)?;
+        let labels = self.regex.capture_names()
+            .flatten()
+            .filter(|name| *name != "value")
+            .map(|name| (String::from(name), captures.name(name).map(|m| String::from(m.as_str())).unwrap_or_default()))
+            .collect();
+        let value = self.value.and_then(|idx| captures.get(idx)).and_then(|m| m.as_str().parse().ok());
+        Some((labels, value))
+    }
+}
+
+/// Determines if `name` matches `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'),
+        _ => false,
+    }
+}
+
+/// Determines if `name` matches `[a-zA-Z_][a-zA-Z0-9_]*` and isn't reserved (`__` prefix).
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    };
+    valid && !name.starts_with("__")
+}
+
+/// Current value of a metric.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Counter(f64),
+    Gauge(f64),
+    /// Observations by bucket (not cumulative, the last one is `+Inf`), sum and count.
+    Histogram(Vec<u64>, f64, u64),
+}
+
+/// Values of the metrics of the rules, by rule and labels.
+type Values = Vec<BTreeMap<Labels, Value>>;
+
+/// State shared by the handler and `ExtractedMetrics`.
+struct State {
+    rules: Vec<MetricRule>,
+    values: Values,
+}
+
+/// Shared access to the metrics of an `ExtractHandler`.
+#[derive(Clone)]
+pub struct ExtractedMetrics {
+    state: Arc<Mutex<State>>,
+}
+
+impl ExtractedMetrics {
+    /// Value of a counter or gauge.
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        match self.find(name, labels) {
+            Some(Value::Counter(value)) | Some(Value::Gauge(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Cumulative counts by bucket (the last one is `+Inf`), sum and count of a histogram.
+    pub fn histogram(&self, name: &str, labels: &[(&str, &str)]) -> Option<(Vec<u64>, f64, u64)> {
+        match self.find(name, labels) {
+            Some(Value::Histogram(buckets, sum, count)) => Some((cumulate(&buckets), sum, count)),
+            _ => None,
+        }
+    }
+
+    fn find(&self, name: &str, labels: &[(&str, &str)]) -> Option<Value> {
+        let labels: Labels = labels.iter().map(|&(k, v)| (String::from(k), String::from(v))).collect();
+        let state = self.state.lock().unwrap();
+        let idx = state.rules.iter().position(|rule| rule.name == name)?;
+        state.values[idx].get(&labels).cloned()
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut text = String::new();
+        for (rule, values) in state.rules.iter().zip(state.values.iter()) {
+            let kind = match rule.kind {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+                MetricKind::Histogram(_) => "histogram",
+            };
+            let _ = write!(text, "# HELP {0} {1}\n# TYPE {0} {2}\n", rule.name, escape_help(&rule.help), kind);
+            for (labels, value) in values.iter() {
+                match *value {
+                    Value::Counter(value) | Value::Gauge(value) => {
+                        let _ = writeln!(text, "{}{} {}", rule.name, format_labels(labels, None), value);
+                    }
+                    Value::Histogram(ref buckets, sum, count) => {
+                        let bounds = match rule.kind {
+                            MetricKind::Histogram(ref bounds) => bounds,
+                            _ => continue,
+                        };
+                        for (idx, count) in cumulate(buckets).iter().enumerate() {
+                            let le = bounds.get(idx).map(|bound| bound.to_string()).unwrap_or_else(|| String::from("+Inf"));
+                            let _ = writeln!(text, "{}_bucket{} {}", rule.name, format_labels(labels, Some(&le)), count);
+                        }
+                        let _ = writeln!(text, "{}_sum{} {}", rule.name, format_labels(labels, None), sum);
+                        let _ = writeln!(text, "{}_count{} {}", rule.name, format_labels(labels, None), count);
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+/// Cumulative counts of histogram buckets.
+fn cumulate(buckets: &[u64]) -> Vec<u64> {
+    buckets.iter().scan(0, |total, count| {
+        *total += *count;
+        Some(*total)
+    }).collect()
+}
+
+/// Format the labels of a sample, with the `le` label of the histogram buckets.
+fn format_labels(labels: &[(String, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))).collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() { String::new() } else { format!("{{{}}}", pairs.join(",")) }
+}
+
+/// Handler which applies `MetricRule`s to the records to feed counters, gauges and histograms.
+///
+/// It derives metrics from log lines without changing the code which logs them. The metrics are
+/// read through `ExtractedMetrics`, which also renders them in the Prometheus text format.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut hdlr = ExtractHandler::new(
+///     vec![MetricRule::new("db_query_duration_ms", r"took (\d+)ms", MetricKind::Histogram(vec![10.0, 100.0, 1000.0])).unwrap()],
+///     Some(LogLevelFilter::Info),
+/// );
+/// let metrics = hdlr.metrics();
+///
+/// hdlr.handle(&rec);
+///
+/// println!("{}", metrics.render());
+/// ```
+pub struct ExtractHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The rules and the values of their metrics.
+    state: Arc<Mutex<State>>,
+}
+
+impl ExtractHandler {
+    /// Create a new handler instance.
+    pub fn new(rules: Vec<MetricRule>, level: Option<LogLevelFilter>) -> ExtractHandler {
+        let values = rules.iter().map(|_| BTreeMap::new()).collect();
+        ExtractHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            state: Arc::new(Mutex::new(State { rules, values })),
+        }
+    }
+
+    /// Shared access to the metrics of the handler.
+    pub fn metrics(&self) -> ExtractedMetrics {
+        ExtractedMetrics { state: self.state.clone() }
+    }
+}
+
+impl Filter for ExtractHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for ExtractHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Feed the metrics of the rules matching the record.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let State { ref rules, ref mut values } = *state;
+        for (rule, values) in rules.iter().zip(values.iter_mut()) {
+            let (labels, value) = match rule.extract(record) {
+                Some(extracted) => extracted,
+                None => continue,
+            };
+            match rule.kind {
+                MetricKind::Counter => {
+                    let increment = if rule.value.is_some() { value } else { Some(1.0) };
+                    if let Some(increment) = increment.filter(|increment| *increment >= 0.0) {
+                        if let Value::Counter(ref mut total) = *values.entry(labels).or_insert(Value::Counter(0.0)) {
+                            *total += increment;
+                        }
+                    }
+                }
+                MetricKind::Gauge => {
+                    if let Some(value) = value {
+                        values.insert(labels, Value::Gauge(value));
+                    }
+                }
+                MetricKind::Histogram(ref bounds) => {
+                    if let Some(value) = value {
+                        let entry = values.entry(labels).or_insert_with(|| Value::Histogram(vec![0; bounds.len() + 1], 0.0, 0));
+                        if let Value::Histogram(ref mut buckets, ref mut sum, ref mut count) = *entry {
+                            let idx = bounds.iter().position(|bound| value <= *bound).unwrap_or(bounds.len());
+                            buckets[idx] += 1;
+                            *sum += value;
+                            *count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
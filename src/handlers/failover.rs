@@ -0,0 +1,138 @@
+//!
+//! A handler to switch to a secondary handler when the primary one fails.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::io;
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// Handler which sends the records to `primary`, and to `secondary` while `primary` fails.
+///
+/// When the primary handler fails to emit a record, the record is sent to the secondary handler
+/// which receives all the following records. Every `probe_interval`, the next record is sent to
+/// the primary handler again to check whether it recovered.
+///
+/// Each switch is reported by a record sent to the handler taking over: a `WARN` record when
+/// failing over to the secondary handler, and an `INFO` one when switching back to the primary.
+///
+/// The primary handler must be able to recover by itself, e.g. a `PipeHandler` restarting its
+/// command or a `TCPHandler` reconnecting to its server. It must fail only when it drops the
+/// record: a handler which keeps the record to send it later, like a `PipeHandler` with a buffer,
+/// would deliver it twice. Give a `PipeHandler` a `capacity` of 0 to use it as primary handler.
+///
+/// # Examples
+///
+/// Write the records into a local file while the collector is down:
+///
+/// ```rust
+/// let mut shipper = PipeHandler::new("shipper", &["--collector", "10.0.0.1"], Some(LogLevelFilter::Info), Some(json));
+/// shipper.capacity = 0;
+/// let mut hdlr = FailoverHandler::new(
+///     Handler::from(shipper),
+///     Handler::from(FileHandler::new("/tmp/log-fallback.txt", Some(LogLevelFilter::Info), Some(json))),
+///     Some(LogLevelFilter::Info),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct FailoverHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The preferred handler.
+    pub primary: Box<Handler>,
+    /// The handler used while the primary handler fails.
+    pub secondary: Box<Handler>,
+    /// Delay between two attempts to switch back to the primary handler.
+    pub probe_interval: Duration,
+    /// When failed over, the last time the primary handler failed.
+    failed_at: Option<Instant>,
+    /// Number of switches between the handlers.
+    switches: u64,
+}
+
+impl FailoverHandler {
+    /// Create a new handler instance.
+    pub fn new(primary: Handler, secondary: Handler, level: Option<LogLevelFilter>) -> FailoverHandler {
+        FailoverHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            primary: Box::new(primary),
+            secondary: Box::new(secondary),
+            probe_interval: Duration::from_secs(30),
+            failed_at: None,
+            switches: 0,
+        }
+    }
+
+    /// Determines if the records are currently sent to the secondary handler.
+    pub fn failed_over(&self) -> bool {
+        self.failed_at.is_some()
+    }
+
+    /// Number of switches between the handlers.
+    pub fn switches(&self) -> u64 {
+        self.switches
+    }
+
+    /// Send the record to the secondary handler.
+    fn fail_over(&mut self, record: &ExtendedLogRecord, err: io::Error) -> io::Result<()> {
+        if self.failed_at.is_none() {
+            self.switches += 1;
+            let _ = self.secondary.handle(&report(
+                LogLevel::Warn,
+                format!("primary handler failed ({}), switching to the secondary handler", err),
+            ));
+        }
+        self.failed_at = Some(Instant::now());
+        self.secondary.handle(record)
+    }
+}
+
+/// Build the record which reports a switch.
+fn report(level: LogLevel, msg: String) -> ExtendedLogRecord<'static> {
+    ExtendedLogRecord::new(file!(), level, line!(), module_path!(), msg, String::from(module_path!()))
+}
+
+impl Filter for FailoverHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for FailoverHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the record to the active handler, switching handlers if needed.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        match self.failed_at {
+            Some(failed_at) if failed_at.elapsed() < self.probe_interval => self.secondary.handle(record),
+            Some(_) => match self.primary.handle(record) {
+                Ok(()) => {
+                    self.failed_at = None;
+                    self.switches += 1;
+                    let _ = self.primary.handle(&report(
+                        LogLevel::Info,
+                        String::from("primary handler recovered, switching back from the secondary handler"),
+                    ));
+                    Ok(())
+                }
+                Err(err) => self.fail_over(record, err),
+            },
+            None => match self.primary.handle(record) {
+                Ok(()) => Ok(()),
+                Err(err) => self.fail_over(record, err),
+            },
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//!
+//! A handler to buffer log records in memory and flush them to another handler on demand.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::collections::VecDeque;
+use std::io;
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// Handler which keeps the last records in memory and flushes them to a wrapped handler when a
+/// record at or above `flush_level` is received.
+///
+/// It gives the context of a failure without writing every record: the buffer accepts records up
+/// to `level`, and only the `capacity` most recent ones are kept. With a `capacity` of 0, only the
+/// records at or above `flush_level` are sent.
+///
+/// The wrapped handler still applies its own level and filters on the flushed records.
+///
+/// # Examples
+///
+/// Write the 100 last DEBUG records into a file when an ERROR occurs:
+///
+/// ```rust
+/// let mut hdlr = MemoryHandler::new(
+///     Handler::from(FileHandler::new("/tmp/log-error.txt", Some(LogLevelFilter::Debug), Some(json))),
+///     100,
+///     LogLevel::Error,
+///     Some(LogLevelFilter::Debug),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct MemoryHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Maximum number of buffered records.
+    pub capacity: usize,
+    /// Records at or above this level flush the buffer.
+    pub flush_level: LogLevel,
+    /// The handler which receives the flushed records.
+    pub target: Box<Handler>,
+    /// The buffered records.
+    buffer: VecDeque<OwnedLogRecord>,
+}
+
+impl MemoryHandler {
+    /// Create a new handler instance which flushes into `target`.
+    pub fn new(target: Handler, capacity: usize, flush_level: LogLevel, level: Option<LogLevelFilter>) -> MemoryHandler {
+        MemoryHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            capacity,
+            flush_level,
+            target: Box::new(target),
+            buffer: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Number of buffered records.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Determines if no record is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Send all the buffered records to the wrapped handler.
+    ///
+    /// Every record is sent even if the wrapped handler fails, the first error is returned.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for record in self.buffer.drain(..) {
+            let emitted = self.target.handle(&record.as_record());
+            if result.is_ok() {
+                result = emitted;
+            }
+        }
+        result
+    }
+}
+
+impl Filter for MemoryHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for MemoryHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Buffer the record, and flush the buffer if the record reaches `flush_level`.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.capacity == 0 {
+            // Nothing is buffered, only the records reaching `flush_level` go through.
+            return if record.level() <= self.flush_level { self.target.handle(record) } else { Ok(()) };
+        }
+        if self.buffer.len() >= self.capacity {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(OwnedLogRecord::from(record));
+        if record.level() <= self.flush_level {
+            return self.flush();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+//!
+//! A handler to count log records and expose the counts to Prometheus.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ExtendedLogRecord;
+
+/// Name of the exposed counter.
+const METRIC_NAME: &str = "log_records_total";
+
+/// Labels of a counter: level, target and module if counted by module.
+type Labels = (String, String, Option<String>);
+
+/// Shared access to the counters of a `MetricsHandler`.
+///
+/// It may be cloned and kept by the application, for instance to add the counts to the page
+/// of an existing metrics endpoint.
+#[derive(Clone)]
+pub struct Metrics {
+    counters: Arc<Mutex<BTreeMap<Labels, u64>>>,
+}
+
+impl Metrics {
+    /// Number of records counted at `level` (e.g. `"ERROR"`) for `target`, for all modules.
+    pub fn count(&self, level: &str, target: &str) -> u64 {
+        self.counters.lock().unwrap().iter()
+            .filter(|&(labels, _)| labels.0 == level && labels.1 == target)
+            .map(|(_, count)| *count)
+            .sum()
+    }
+
+    /// Reset all the counters.
+    pub fn clear(&self) {
+        self.counters.lock().unwrap().clear();
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "# HELP {0} Number of log records by level and target.\n# TYPE {0} counter\n",
+            METRIC_NAME
+        );
+        for ((level, target, module), count) in self.counters.lock().unwrap().iter() {
+            let _ = write!(text, "{}{{level=\"{}\",target=\"{}\"", METRIC_NAME, escape(level), escape(target));
+            if let Some(module) = module {
+                let _ = write!(text, ",module=\"{}\"", escape(module));
+            }
+            let _ = writeln!(text, "}} {}", count);
+        }
+        text
+    }
+
+    /// Serve the counters on `http://<address>/metrics` from a background thread and return
+    /// the address the server listens on.
+    ///
+    /// Each connection is answered by its own thread, and dropped if the request is not received
+    /// within 5 seconds.
+    pub fn serve(&self, address: &str) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let metrics = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let metrics = metrics.clone();
+                thread::spawn(move || metrics.answer(stream));
+            }
+        });
+        Ok(address)
+    }
+
+    /// Answer an HTTP request with the counters or a 404 error.
+    fn answer(&self, stream: TcpStream) -> io::Result<()> {
+        let timeout = Some(Duration::from_secs(5));
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+        }
+        let (status, body) = match request.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", self.render()),
+            _ => ("404 Not Found", String::from("Not Found\n")),
+        };
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body
+        )
+    }
+}
+
+/// Escape a label value of the Prometheus text format (backslash, double quote and line feed).
+pub fn escape(value: &str) -> String {
+    escape_help(value).replace('"', "\\\"")
+}
+
+/// Escape a `HELP` docstring of the Prometheus text format (backslash and line feed).
+pub fn escape_help(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Handler which counts the records by level and target, and optionally by module.
+///
+/// The counts are exposed as the `log_records_total` counter, through `Metrics::render` or the
+/// embedded HTTP server started by `Metrics::serve`.
+///
+/// # Examples
+///
+/// Alert on the error rate per subsystem with `rate(log_records_total{level="ERROR"}[5m])`:
+///
+/// ```rust
+/// let mut hdlr = MetricsHandler::new(Some(LogLevelFilter::Info));
+/// let metrics = hdlr.metrics();
+/// metrics.serve("0.0.0.0:9100").unwrap();
+///
+/// hdlr.handle(&rec);
+///
+/// println!("{}", metrics.render());
+/// ```
+pub struct MetricsHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Count the records by module too.
+    pub per_module: bool,
+    /// The counters shared with `Metrics`.
+    counters: Arc<Mutex<BTreeMap<Labels, u64>>>,
+}
+
+impl MetricsHandler {
+    /// Create a new handler instance.
+    pub fn new(level: Option<LogLevelFilter>) -> MetricsHandler {
+        MetricsHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            per_module: false,
+            counters: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Shared access to the counters of the handler.
+    pub fn metrics(&self) -> Metrics {
+        Metrics { counters: self.counters.clone() }
+    }
+}
+
+impl Filter for MetricsHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for MetricsHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Increment the counter of the record.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let module = if self.per_module { Some(String::from(record.module)) } else { None };
+        let labels = (record.level.clone(), record.target.clone(), module);
+        *self.counters.lock().unwrap().entry(labels).or_insert(0) += 1;
+        Ok(())
+    }
+}
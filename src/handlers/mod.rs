@@ -1,13 +1,59 @@
 //!
 //! Module which provide handlers to send the log records to the appropriate destination.
 //!
+pub mod alert;
+#[macro_use]
+pub mod capture;
+pub mod channel;
+pub mod custom;
+pub mod dedup;
+pub mod digest;
+pub mod extract;
+pub mod failover;
+pub mod memory;
+pub mod metrics;
+pub mod pipe;
+pub mod queue;
+pub mod ratelimit;
+pub mod ring;
+pub mod routing;
+pub mod sampling;
+pub mod scope;
+pub mod statsd;
 pub mod streams;
+pub mod tail;
+pub mod webhook;
+pub mod websocket;
 
+use handlers::alert::AlertHandler;
+use handlers::capture::CaptureHandler;
+use handlers::channel::ChannelHandler;
+use handlers::custom::CustomHandler;
+use handlers::dedup::DedupHandler;
+use handlers::digest::DigestHandler;
+use handlers::extract::ExtractHandler;
+use handlers::failover::FailoverHandler;
+use handlers::memory::MemoryHandler;
+use handlers::metrics::MetricsHandler;
+use handlers::pipe::PipeHandler;
+use handlers::queue::QueueHandler;
+use handlers::ratelimit::RateLimitHandler;
+use handlers::ring::RingHandler;
+use handlers::routing::RoutingHandler;
+use handlers::sampling::SamplingHandler;
+use handlers::scope::ScopeHandler;
+use handlers::statsd::StatsdHandler;
 use handlers::streams::file::FileHandler;
 use handlers::streams::net::TCPHandler;
+use handlers::streams::stderr::StderrHandler;
 use handlers::streams::stdout::StdoutHandler;
+use handlers::streams::writer::WriterHandler;
+use handlers::tail::TailHandler;
+use handlers::webhook::WebhookHandler;
+use handlers::websocket::WebSocketHandler;
 use log::LogLevelFilter;
 use ExtendedLogRecord;
+use std::io;
 use std::sync::Mutex;
 
 /// A trait encapsulating the filtering operation of the handler.
@@ -23,11 +69,37 @@ lazy_static! {
 }
 
 /// A trait encapsulating the operations required of a handler
+///
+/// Implement it to plug a user defined handler into the logger using
+/// `ExtendedLogger::add_custom_handler`, which wraps it into a `CustomHandler` applying the level
+/// and filters.
+///
+/// # Examples
+///
+/// ```rust
+/// struct CountHandler {
+///     count: usize,
+/// }
+///
+/// impl Handle for CountHandler {
+///     fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+///         self.emit(record)
+///     }
+///     fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+///         self.count += 1;
+///         Ok(())
+///     }
+/// }
+///
+/// ExtendedLogger::add_custom_handler(CountHandler { count: 0 }, Some(LogLevelFilter::Error));
+/// ```
 pub trait Handle {
     /// Determines if a log record may be handled by the handler.
-    fn handle(&mut self, record: &ExtendedLogRecord);
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()>;
     /// Emit the log record.
-    fn emit(&mut self, record: &ExtendedLogRecord);
+    ///
+    /// An error means the record was not delivered; it is up to the caller to recover.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()>;
 }
 
 /// Available handlers
@@ -39,17 +111,86 @@ pub enum Handler {
     /// A handler to send the log record into a file.
     File(FileHandler),
     /// A handler to send the log record into a TCP socket.
-    TCP(TCPHandler)
+    TCP(TCPHandler),
+    /// A handler to post the log record to a chat webhook.
+    Webhook(WebhookHandler),
+    /// A handler to send the log record into the standard input of a command.
+    Pipe(PipeHandler),
+    /// A handler to stream the log record to the clients of a TCP server.
+    Tail(TailHandler),
+    /// A handler to push the log record to WebSocket clients.
+    WebSocket(WebSocketHandler),
+    /// A handler to send the log record into stderr.
+    Stderr(StderrHandler),
+    /// A handler to buffer the log record and flush it to another handler.
+    Memory(MemoryHandler),
+    /// A handler to keep the most recent log records in memory.
+    Ring(RingHandler),
+    /// A handler to send the log record to other handlers from a background thread.
+    Queue(QueueHandler),
+    /// A handler to deliver the log record to the application.
+    Channel(ChannelHandler),
+    /// A user defined handler.
+    Custom(CustomHandler),
+    /// A handler to send the log record into any writer.
+    Writer(WriterHandler),
+    /// A handler to dispatch the log record to other handlers.
+    Routing(RoutingHandler),
+    /// A handler to switch to a secondary handler when the primary one fails.
+    Failover(FailoverHandler),
+    /// A handler to check the log records from tests.
+    Capture(CaptureHandler),
+    /// A handler to count log records and expose the counts to Prometheus.
+    Metrics(MetricsHandler),
+    /// A handler to suppress duplicate log records.
+    Dedup(DedupHandler),
+    /// A handler to rate-limit log records with token buckets.
+    RateLimit(RateLimitHandler),
+    /// A handler to keep a fraction of the log records of each level.
+    Sampling(SamplingHandler),
+    /// A handler to aggregate log records into periodic summaries.
+    Digest(DigestHandler),
+    /// A handler to raise alerts when targets log too many records.
+    Alert(AlertHandler),
+    /// A handler to keep the whole trace of failed requests only.
+    Scope(ScopeHandler),
+    /// A handler to count log records with a StatsD agent.
+    Statsd(StatsdHandler),
+    /// A handler to extract metrics from the messages of the log records.
+    Extract(ExtractHandler)
 }
 
 impl Handler {
-    pub fn handle(&mut self, record: &ExtendedLogRecord) {
+    pub fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
         match *self {
             Handler::Null(ref mut hdlr) => hdlr.handle(record),
             Handler::Stdout(ref mut hdlr) => hdlr.handle(record),
             Handler::File(ref mut hdlr) => hdlr.handle(record),
             Handler::TCP(ref mut hdlr) => hdlr.handle(record),
-        };
+            Handler::Webhook(ref mut hdlr) => hdlr.handle(record),
+            Handler::Pipe(ref mut hdlr) => hdlr.handle(record),
+            Handler::Tail(ref mut hdlr) => hdlr.handle(record),
+            Handler::WebSocket(ref mut hdlr) => hdlr.handle(record),
+            Handler::Stderr(ref mut hdlr) => hdlr.handle(record),
+            Handler::Memory(ref mut hdlr) => hdlr.handle(record),
+            Handler::Ring(ref mut hdlr) => hdlr.handle(record),
+            Handler::Queue(ref mut hdlr) => hdlr.handle(record),
+            Handler::Channel(ref mut hdlr) => hdlr.handle(record),
+            Handler::Custom(ref mut hdlr) => hdlr.handle(record),
+            Handler::Writer(ref mut hdlr) => hdlr.handle(record),
+            Handler::Routing(ref mut hdlr) => hdlr.handle(record),
+            Handler::Failover(ref mut hdlr) => hdlr.handle(record),
+            Handler::Capture(ref mut hdlr) => hdlr.handle(record),
+            Handler::Metrics(ref mut hdlr) => hdlr.handle(record),
+            Handler::Dedup(ref mut hdlr) => hdlr.handle(record),
+            Handler::RateLimit(ref mut hdlr) => hdlr.handle(record),
+            Handler::Sampling(ref mut hdlr) => hdlr.handle(record),
+            Handler::Digest(ref mut hdlr) => hdlr.handle(record),
+            Handler::Alert(ref mut hdlr) => hdlr.handle(record),
+            Handler::Scope(ref mut hdlr) => hdlr.handle(record),
+            Handler::Statsd(ref mut hdlr) => hdlr.handle(record),
+            Handler::Extract(ref mut hdlr) => hdlr.handle(record),
+        }
     }
 }
 
@@ -77,6 +218,151 @@ impl From<TCPHandler> for Handler {
     }
 }
 
+impl From<WebhookHandler> for Handler {
+    fn from(hdlr: WebhookHandler) -> Handler {
+        Handler::Webhook(hdlr)
+    }
+}
+
+impl From<PipeHandler> for Handler {
+    fn from(hdlr: PipeHandler) -> Handler {
+        Handler::Pipe(hdlr)
+    }
+}
+
+impl From<TailHandler> for Handler {
+    fn from(hdlr: TailHandler) -> Handler {
+        Handler::Tail(hdlr)
+    }
+}
+
+impl From<WebSocketHandler> for Handler {
+    fn from(hdlr: WebSocketHandler) -> Handler {
+        Handler::WebSocket(hdlr)
+    }
+}
+
+impl From<StderrHandler> for Handler {
+    fn from(hdlr: StderrHandler) -> Handler {
+        Handler::Stderr(hdlr)
+    }
+}
+
+impl From<MemoryHandler> for Handler {
+    fn from(hdlr: MemoryHandler) -> Handler {
+        Handler::Memory(hdlr)
+    }
+}
+
+impl From<RingHandler> for Handler {
+    fn from(hdlr: RingHandler) -> Handler {
+        Handler::Ring(hdlr)
+    }
+}
+
+impl From<QueueHandler> for Handler {
+    fn from(hdlr: QueueHandler) -> Handler {
+        Handler::Queue(hdlr)
+    }
+}
+
+impl From<ChannelHandler> for Handler {
+    fn from(hdlr: ChannelHandler) -> Handler {
+        Handler::Channel(hdlr)
+    }
+}
+
+impl From<CustomHandler> for Handler {
+    fn from(hdlr: CustomHandler) -> Handler {
+        Handler::Custom(hdlr)
+    }
+}
+
+impl From<Box<dyn Handle + Send>> for Handler {
+    /// Wrap a user defined handler into a `CustomHandler` letting all the records through.
+    fn from(hdlr: Box<dyn Handle + Send>) -> Handler {
+        Handler::Custom(CustomHandler::new(hdlr, Some(LogLevelFilter::Trace)))
+    }
+}
+
+impl From<WriterHandler> for Handler {
+    fn from(hdlr: WriterHandler) -> Handler {
+        Handler::Writer(hdlr)
+    }
+}
+
+impl From<RoutingHandler> for Handler {
+    fn from(hdlr: RoutingHandler) -> Handler {
+        Handler::Routing(hdlr)
+    }
+}
+
+impl From<FailoverHandler> for Handler {
+    fn from(hdlr: FailoverHandler) -> Handler {
+        Handler::Failover(hdlr)
+    }
+}
+
+impl From<CaptureHandler> for Handler {
+    fn from(hdlr: CaptureHandler) -> Handler {
+        Handler::Capture(hdlr)
+    }
+}
+
+impl From<MetricsHandler> for Handler {
+    fn from(hdlr: MetricsHandler) -> Handler {
+        Handler::Metrics(hdlr)
+    }
+}
+
+impl From<DedupHandler> for Handler {
+    fn from(hdlr: DedupHandler) -> Handler {
+        Handler::Dedup(hdlr)
+    }
+}
+
+impl From<RateLimitHandler> for Handler {
+    fn from(hdlr: RateLimitHandler) -> Handler {
+        Handler::RateLimit(hdlr)
+    }
+}
+
+impl From<SamplingHandler> for Handler {
+    fn from(hdlr: SamplingHandler) -> Handler {
+        Handler::Sampling(hdlr)
+    }
+}
+
+impl From<DigestHandler> for Handler {
+    fn from(hdlr: DigestHandler) -> Handler {
+        Handler::Digest(hdlr)
+    }
+}
+
+impl From<AlertHandler> for Handler {
+    fn from(hdlr: AlertHandler) -> Handler {
+        Handler::Alert(hdlr)
+    }
+}
+
+impl From<ScopeHandler> for Handler {
+    fn from(hdlr: ScopeHandler) -> Handler {
+        Handler::Scope(hdlr)
+    }
+}
+
+impl From<StatsdHandler> for Handler {
+    fn from(hdlr: StatsdHandler) -> Handler {
+        Handler::Statsd(hdlr)
+    }
+}
+
+impl From<ExtractHandler> for Handler {
+    fn from(hdlr: ExtractHandler) -> Handler {
+        Handler::Extract(hdlr)
+    }
+}
+
 ///
 /// A dummy handler which does nothing
 ///
@@ -88,6 +374,6 @@ impl Filter for NullHandler {
 }
 
 impl Handle for NullHandler {
-    fn handle(&mut self, record: &ExtendedLogRecord) {}
-    fn emit(&mut self, record: &ExtendedLogRecord) {}
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> { Ok(()) }
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> { Ok(()) }
 }
\ No newline at end of file
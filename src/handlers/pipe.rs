@@ -0,0 +1,295 @@
+//!
+//! A handler to feed log records into the standard input of a child process.
+//!
+
+use formatter::default;
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::cmp;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// Lines shared by the handler and the thread writing them to the command.
+struct Lines {
+    /// Formatted records not yet written.
+    pending: VecDeque<String>,
+    /// Incremented when the command is restarted, the writer of the previous one exits.
+    generation: u64,
+    /// Set by the writer when the standard input of the command is broken.
+    broken: bool,
+    /// Set by the writer once it wrote records, the command is then considered healthy.
+    written: bool,
+    /// Set when the handler is dropped, the writer exits once the lines are written.
+    closed: bool,
+}
+
+/// Queue of the lines and its condition, notified when lines are pushed or the state changes.
+struct Queue {
+    lines: Mutex<Lines>,
+    changed: Condvar,
+}
+
+/// Write the queued lines into the standard input of the command until it breaks, the command is
+/// restarted or the handler is dropped.
+fn write(mut stdin: ChildStdin, queue: Arc<Queue>, generation: u64) {
+    loop {
+        let lines: Vec<String> = {
+            let mut lines = queue.lines.lock().unwrap();
+            while lines.pending.is_empty() && !lines.closed && lines.generation == generation {
+                lines = queue.changed.wait(lines).unwrap();
+            }
+            if lines.pending.is_empty() || lines.generation != generation {
+                return;
+            }
+            lines.pending.drain(..).collect()
+        };
+        for (idx, line) in lines.iter().enumerate() {
+            if stdin.write_all(line.as_bytes()).is_err() {
+                let mut shared = queue.lines.lock().unwrap();
+                for line in lines[idx..].iter().rev() {
+                    shared.pending.push_front(line.clone());
+                }
+                shared.broken = true;
+                return;
+            }
+        }
+        let flushed = stdin.flush();
+        let mut shared = queue.lines.lock().unwrap();
+        if flushed.is_err() {
+            shared.broken = true;
+            return;
+        }
+        shared.written = true;
+    }
+}
+
+/// Handler which writes formatted records into the standard input of a command.
+///
+/// The command is spawned on the first record, and the records are written by a dedicated thread
+/// so that a command which stops reading never blocks the logger. When the command exits or
+/// cannot be spawned, it is restarted with an exponential backoff between `min_backoff` and
+/// `max_backoff`. The command is only restarted when a record is handled or `flush` is called.
+///
+/// The records waiting for the command are kept in a buffer of at most `capacity` records, the
+/// oldest ones being dropped first. A buffered record is not lost, so handling it succeeds even
+/// if the command is down. With a `capacity` of 0, nothing is buffered: handling fails when the
+/// command is down or still writing the previous record, which lets a `FailoverHandler` send it
+/// elsewhere.
+///
+/// Dropping the handler restarts the command right away if needed, waits for the buffer to be
+/// written and for the command to exit once its standard input is closed, at most `stop_timeout`
+/// before killing it.
+///
+/// # Examples
+///
+/// Send the records to syslog using `logger`:
+///
+/// ```rust
+/// let mut hdlr = PipeHandler::new(
+///     "logger",
+///     &["-t", "app"],
+///     Some(LogLevelFilter::Info),
+///     None,
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct PipeHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// Callback to format log record.
+    pub formatter: fn(&ExtendedLogRecord) -> String,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Maximum number of records waiting for the command.
+    pub capacity: usize,
+    /// Delay before the first restart of the command.
+    pub min_backoff: Duration,
+    /// Maximum delay between two restarts of the command.
+    pub max_backoff: Duration,
+    /// Delay given to the command to exit once its standard input is closed, before it is killed.
+    pub stop_timeout: Duration,
+    /// Program to run.
+    program: String,
+    /// Arguments of the program.
+    args: Vec<String>,
+    /// The running command.
+    child: Option<Child>,
+    /// The lines waiting for the writer.
+    queue: Arc<Queue>,
+    /// Number of consecutive failures of the command.
+    failures: u32,
+    /// Earliest time the command may be restarted.
+    retry_at: Option<Instant>,
+}
+
+impl PipeHandler {
+    /// Create a new handler instance. The command is spawned on the first record.
+    pub fn new(program: &str, args: &[&str], level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> PipeHandler {
+        PipeHandler {
+            filters: vec![],
+            formatter: formatter.unwrap_or(default),
+            level: level.unwrap_or(LogLevelFilter::Off),
+            capacity: 10000,
+            min_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(60),
+            stop_timeout: Duration::from_secs(5),
+            program: String::from(program),
+            args: args.iter().map(|arg| String::from(*arg)).collect(),
+            child: None,
+            queue: Arc::new(Queue {
+                lines: Mutex::new(Lines { pending: VecDeque::new(), generation: 0, broken: false, written: false, closed: false }),
+                changed: Condvar::new(),
+            }),
+            failures: 0,
+            retry_at: None,
+        }
+    }
+
+    /// Number of records waiting for the command.
+    pub fn pending(&self) -> usize {
+        self.queue.lines.lock().unwrap().pending.len()
+    }
+
+    /// Restart the command if needed, so that the buffered records are written.
+    ///
+    /// Fails while the command can't be restarted, the records stay buffered meanwhile.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let started = self.start();
+        self.queue.changed.notify_all();
+        started
+    }
+
+    /// Make sure the command is running, restarting it once the backoff is over.
+    fn start(&mut self) -> io::Result<()> {
+        if let Some(ref mut child) = self.child {
+            let broken = self.queue.lines.lock().unwrap().broken;
+            if !broken && child.try_wait()?.is_none() {
+                return Ok(());
+            }
+        }
+        if self.child.is_some() {
+            self.kill();
+            self.backoff();
+        }
+        if let Some(retry_at) = self.retry_at {
+            if Instant::now() < retry_at {
+                return Err(io::Error::new(io::ErrorKind::NotConnected, "waiting to restart the command"));
+            }
+        }
+        if let Err(err) = self.spawn() {
+            self.backoff();
+            return Err(err);
+        }
+        self.retry_at = None;
+        Ok(())
+    }
+
+    /// Spawn the command and its writer.
+    fn spawn(&mut self) -> io::Result<()> {
+        let mut child = Command::new(&self.program).args(&self.args).stdin(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let generation = {
+            let mut lines = self.queue.lines.lock().unwrap();
+            lines.generation += 1;
+            lines.broken = false;
+            lines.generation
+        };
+        self.queue.changed.notify_all();
+        let queue = self.queue.clone();
+        thread::spawn(move || write(stdin, queue, generation));
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Delay the next restart of the command, the delay is reset once the command wrote records.
+    fn backoff(&mut self) {
+        if std::mem::replace(&mut self.queue.lines.lock().unwrap().written, false) {
+            self.failures = 0;
+        }
+        self.failures += 1;
+        let backoff = self.min_backoff * 2u32.pow(cmp::min(self.failures - 1, 16));
+        self.retry_at = Some(Instant::now() + cmp::min(backoff, self.max_backoff));
+    }
+
+    /// Kill the command which exited or stopped reading.
+    fn kill(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// Let the writer close the standard input of the command once the lines are written, and
+    /// wait for the command to exit, killing it after `stop_timeout`.
+    fn stop(&mut self) {
+        self.queue.lines.lock().unwrap().closed = true;
+        self.queue.changed.notify_all();
+        if let Some(mut child) = self.child.take() {
+            let deadline = Instant::now() + self.stop_timeout;
+            while let Ok(None) = child.try_wait() {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+impl Drop for PipeHandler {
+    /// Restart the command without waiting for the backoff if records are buffered, then stop it.
+    fn drop(&mut self) {
+        if self.pending() > 0 {
+            self.retry_at = None;
+            let _ = self.start();
+        }
+        self.stop();
+    }
+}
+
+impl Filter for PipeHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for PipeHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Queue the formatted record for the writer, restarting the command if needed.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let line = (self.formatter)(record);
+        let started = self.start();
+        {
+            let mut lines = self.queue.lines.lock().unwrap();
+            if self.capacity == 0 {
+                // Without buffer the record is dropped.
+                started?;
+                if !lines.pending.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::WouldBlock, "the command is still writing"));
+                }
+            } else if lines.pending.len() >= self.capacity {
+                lines.pending.pop_front();
+            }
+            // Otherwise it stays buffered until the command is restarted.
+            lines.pending.push_back(line);
+        }
+        self.queue.changed.notify_all();
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//!
+//! A handler to process log records asynchronously in a background thread.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use handlers::scope::{self, Scope};
+use log::{LogLevel, LogLevelFilter};
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// Behaviour of the `QueueHandler` when its queue is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// Wait for the worker to make room in the queue.
+    Block,
+    /// Drop the incoming record.
+    DropNewest,
+    /// Drop the oldest queued record to make room for the incoming one.
+    DropOldest,
+    /// Drop the incoming record if it is less severe than the level, otherwise wait for the worker.
+    DropBelow(LogLevel),
+}
+
+/// State of the queue shared with the worker.
+struct State {
+    /// The queued records, with the request scope current when they were queued.
+    records: VecDeque<(OwnedLogRecord, Option<Scope>)>,
+    /// Set when the handler is dropped, the worker exits once the queue is empty.
+    closed: bool,
+    /// Number of dropped records.
+    dropped: u64,
+}
+
+/// Queue shared by the handler and its worker.
+struct Queue {
+    /// Maximum number of queued records.
+    capacity: usize,
+    state: Mutex<State>,
+    /// Notified when a record is queued or the handler is dropped.
+    not_empty: Condvar,
+    /// Notified when the worker takes a record.
+    not_full: Condvar,
+}
+
+/// Shared access to the counters of a `QueueHandler`.
+#[derive(Clone)]
+pub struct QueueStats {
+    queue: Arc<Queue>,
+}
+
+impl QueueStats {
+    /// Number of records dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.state.lock().unwrap().dropped
+    }
+
+    /// Number of records waiting for the worker.
+    pub fn pending(&self) -> usize {
+        self.queue.state.lock().unwrap().records.len()
+    }
+}
+
+/// Handler which sends a copy of the records into a bounded queue, drained by a worker thread
+/// into the wrapped handlers.
+///
+/// Slow handlers (network, child processes...) don't hold the logger anymore: only the copy of
+/// the record is made by the logging thread. When the queue is full, `overflow` decides whether
+/// the logging thread waits or a record is dropped; dropped records are counted in `QueueStats`.
+///
+/// The worker handles each record within the `RequestScope` which was current when it was queued.
+///
+/// Dropping the handler (see `ExtendedLogger::shutdown`) waits for the worker to drain the queue.
+///
+/// # Examples
+///
+/// Send the records to a TCP collector without blocking the application, keeping the most
+/// recent records if the collector can't keep up:
+///
+/// ```rust
+/// let mut hdlr = QueueHandler::new(
+///     vec![Handler::from(TCPHandler::new("127.0.0.1:8080", Some(LogLevelFilter::Info), Some(json)))],
+///     10000,
+///     Overflow::DropOldest,
+///     Some(LogLevelFilter::Info),
+/// );
+/// let stats = hdlr.stats();
+///
+/// hdlr.handle(&rec);
+///
+/// println!("{} records dropped", stats.dropped());
+/// ```
+pub struct QueueHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Behaviour when the queue is full.
+    pub overflow: Overflow,
+    /// The queue shared with the worker.
+    queue: Arc<Queue>,
+    /// The worker thread.
+    worker: Option<JoinHandle<()>>,
+}
+
+impl QueueHandler {
+    /// Create a new handler instance and start the worker which owns `handlers`.
+    pub fn new(handlers: Vec<Handler>, capacity: usize, overflow: Overflow, level: Option<LogLevelFilter>) -> QueueHandler {
+        let queue = Arc::new(Queue {
+            capacity: cmp::max(capacity, 1),
+            state: Mutex::new(State { records: VecDeque::new(), closed: false, dropped: 0 }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        });
+        let shared = queue.clone();
+        let worker = thread::spawn(move || work(shared, handlers));
+        QueueHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            overflow,
+            queue,
+            worker: Some(worker),
+        }
+    }
+
+    /// Shared access to the counters of the handler.
+    pub fn stats(&self) -> QueueStats {
+        QueueStats { queue: self.queue.clone() }
+    }
+}
+
+/// Send the queued records to the handlers until the queue is closed and empty.
+fn work(queue: Arc<Queue>, mut handlers: Vec<Handler>) {
+    loop {
+        let (record, scope) = {
+            let mut state = queue.state.lock().unwrap();
+            while state.records.is_empty() && !state.closed {
+                state = queue.not_empty.wait(state).unwrap();
+            }
+            match state.records.pop_front() {
+                Some(record) => record,
+                None => return,
+            }
+        };
+        queue.not_full.notify_one();
+        let record = record.as_record();
+        scope::enter(scope, || {
+            for hdlr in handlers.iter_mut() {
+                // There is nobody to report the error to, the handler has to recover by itself.
+                let _ = hdlr.handle(&record);
+            }
+        });
+    }
+}
+
+impl Drop for QueueHandler {
+    /// Close the queue and wait for the worker to drain it.
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().closed = true;
+        self.queue.not_empty.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Filter for QueueHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for QueueHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Queue a copy of the record, applying the overflow policy if the queue is full.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let mut state = self.queue.state.lock().unwrap();
+        while state.records.len() >= self.queue.capacity {
+            match self.overflow {
+                Overflow::DropOldest => {
+                    state.records.pop_front();
+                    state.dropped += 1;
+                }
+                Overflow::DropBelow(level) if record.level() > level => {
+                    state.dropped += 1;
+                    return Ok(());
+                }
+                Overflow::DropNewest => {
+                    state.dropped += 1;
+                    return Ok(());
+                }
+                Overflow::Block | Overflow::DropBelow(_) => {
+                    state = self.queue.not_full.wait(state).unwrap();
+                }
+            }
+        }
+        state.records.push_back((OwnedLogRecord::from(record), scope::current()));
+        self.queue.not_empty.notify_one();
+        Ok(())
+    }
+}
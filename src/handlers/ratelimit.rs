@@ -0,0 +1,196 @@
+//!
+//! A handler to rate-limit log records with token buckets.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// How the `RateLimitHandler` assigns a bucket to a record.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateLimitKey {
+    /// One bucket per target.
+    Target,
+    /// One bucket per source file and line.
+    Location,
+    /// One bucket per level.
+    Level,
+}
+
+impl RateLimitKey {
+    /// Name of the bucket of a record.
+    fn bucket(&self, record: &ExtendedLogRecord) -> String {
+        match *self {
+            RateLimitKey::Target => record.target.clone(),
+            RateLimitKey::Location => format!("{}:{}", record.file, record.line),
+            RateLimitKey::Level => record.level.clone(),
+        }
+    }
+}
+
+/// A token bucket.
+struct Bucket {
+    /// Available tokens, a record consumes one token.
+    tokens: f64,
+    /// Last time the bucket was refilled.
+    refilled: Instant,
+    /// Number of records suppressed since the last notice.
+    suppressed: u64,
+}
+
+/// Handler which sends the records to `target` as long as their bucket has tokens.
+///
+/// Each bucket holds up to `burst` tokens and is refilled with `refill` tokens per second. A
+/// record consumes one token of its bucket, or is suppressed when the bucket is empty: the first
+/// occurrences of a flood are kept, only the following ones are dropped.
+///
+/// Every `notice_interval`, a `WARN` record `suppressed N records from <bucket>` is sent to the
+/// target handler for each bucket which suppressed records. Pending notices are sent when the
+/// handler is dropped.
+///
+/// # Examples
+///
+/// Allow bursts of 100 records per call site, then 10 records per second:
+///
+/// ```rust
+/// let mut hdlr = RateLimitHandler::new(
+///     Handler::from(FileHandler::new("/tmp/log.txt", Some(LogLevelFilter::Info), None)),
+///     RateLimitKey::Location,
+///     100,
+///     10.0,
+///     Some(LogLevelFilter::Info),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct RateLimitHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The handler which receives the records and the notices.
+    pub target: Box<Handler>,
+    /// How the buckets are assigned.
+    pub key: RateLimitKey,
+    /// Capacity of the buckets.
+    pub burst: u32,
+    /// Tokens added to the buckets every second.
+    pub refill: f64,
+    /// Delay between two notices of suppressed records.
+    pub notice_interval: Duration,
+    /// Source of the current time, `Instant::now` by default.
+    pub clock: fn() -> Instant,
+    /// The buckets by name.
+    buckets: HashMap<String, Bucket>,
+    /// Last time the notices were sent, or the first record was handled.
+    noticed: Option<Instant>,
+}
+
+impl RateLimitHandler {
+    /// Create a new handler instance.
+    pub fn new(target: Handler, key: RateLimitKey, burst: u32, refill: f64, level: Option<LogLevelFilter>) -> RateLimitHandler {
+        RateLimitHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            target: Box::new(target),
+            key,
+            burst,
+            refill,
+            notice_interval: Duration::from_secs(60),
+            clock: Instant::now,
+            buckets: HashMap::new(),
+            noticed: None,
+        }
+    }
+
+    /// Number of records suppressed since the last notice, for all buckets.
+    pub fn suppressed(&self) -> u64 {
+        self.buckets.values().map(|bucket| bucket.suppressed).sum()
+    }
+
+    /// Send the notices of the buckets which suppressed records, and forget the full buckets.
+    fn notify(&mut self) -> io::Result<()> {
+        let mut names: Vec<&String> = self.buckets.iter()
+            .filter(|&(_, bucket)| bucket.suppressed > 0)
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        let mut result = Ok(());
+        for name in names {
+            let sent = self.target.handle(&notice(name, self.buckets[name].suppressed));
+            result = result.and(sent);
+        }
+        let (burst, refill, now) = (self.burst as f64, self.refill, (self.clock)());
+        self.buckets.retain(|_, bucket| {
+            bucket.suppressed = 0;
+            bucket.tokens + duration_secs(now.duration_since(bucket.refilled)) * refill < burst
+        });
+        self.noticed = Some(now);
+        result
+    }
+}
+
+/// Build the record which reports the suppressed records of a bucket.
+fn notice(name: &str, suppressed: u64) -> ExtendedLogRecord<'static> {
+    ExtendedLogRecord::new(
+        file!(),
+        LogLevel::Warn,
+        line!(),
+        module_path!(),
+        format!("suppressed {} records from {}", suppressed, name),
+        String::from(module_path!()),
+    )
+}
+
+/// Duration in seconds.
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+impl Drop for RateLimitHandler {
+    /// Send the pending notices.
+    fn drop(&mut self) {
+        let _ = self.notify();
+    }
+}
+
+impl Filter for RateLimitHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for RateLimitHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the record to the target handler if its bucket has a token, and the due notices.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let now = (self.clock)();
+        let noticed = *self.noticed.get_or_insert(now);
+        let notified = if now.duration_since(noticed) >= self.notice_interval { self.notify() } else { Ok(()) };
+        let (burst, refill) = (self.burst as f64, self.refill);
+        let bucket = self.buckets.entry(self.key.bucket(record)).or_insert(Bucket {
+            tokens: burst,
+            refilled: now,
+            suppressed: 0,
+        });
+        bucket.tokens = (bucket.tokens + duration_secs(now.duration_since(bucket.refilled)) * refill).min(burst);
+        bucket.refilled = now;
+        if bucket.tokens < 1.0 {
+            bucket.suppressed += 1;
+            return notified;
+        }
+        bucket.tokens -= 1.0;
+        notified.and(self.target.handle(record))
+    }
+}
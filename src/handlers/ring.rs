@@ -0,0 +1,180 @@
+//!
+//! A handler to keep the most recent log records in memory and query them.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use rustc_serialize::json;
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// Content of the ring.
+struct Ring {
+    /// The stored records, oldest first.
+    records: VecDeque<OwnedLogRecord>,
+    /// Size of the stored records.
+    bytes: usize,
+    /// Maximum number of records.
+    max_records: Option<usize>,
+    /// Maximum size of the records.
+    max_bytes: Option<usize>,
+}
+
+impl Ring {
+    /// Determines if the ring can store a record of `size` bytes without dropping older records.
+    fn has_room(&self, size: usize) -> bool {
+        self.max_records.map(|max| self.records.len() < max).unwrap_or(true)
+            && self.max_bytes.map(|max| self.bytes + size <= max).unwrap_or(true)
+    }
+}
+
+/// Approximate memory footprint of a record.
+fn size_of(record: &OwnedLogRecord) -> usize {
+    mem::size_of::<OwnedLogRecord>() + record.date.len() + record.file.len() + record.level.len()
+        + record.module.len() + record.msg.len() + record.target.len()
+}
+
+/// Shared access to the records stored by a `RingHandler`.
+///
+/// It may be cloned and kept by the application, for instance to serve the records on a
+/// diagnostic endpoint or to dump them on crash, while the handler is owned by the logger.
+#[derive(Clone)]
+pub struct RingBuffer {
+    ring: Arc<Mutex<Ring>>,
+}
+
+impl RingBuffer {
+    /// Number of stored records.
+    pub fn len(&self) -> usize {
+        self.ring.lock().unwrap().records.len()
+    }
+
+    /// Determines if no record is stored.
+    pub fn is_empty(&self) -> bool {
+        self.ring.lock().unwrap().records.is_empty()
+    }
+
+    /// Size of the stored records, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.ring.lock().unwrap().bytes
+    }
+
+    /// Remove all the stored records.
+    pub fn clear(&self) {
+        let mut ring = self.ring.lock().unwrap();
+        ring.records.clear();
+        ring.bytes = 0;
+    }
+
+    /// Copy of the stored records, oldest first.
+    pub fn snapshot(&self) -> Vec<OwnedLogRecord> {
+        self.ring.lock().unwrap().records.iter().cloned().collect()
+    }
+
+    /// Copy of the stored records up to `level` which target starts with `target`, oldest first.
+    pub fn query(&self, level: LogLevelFilter, target: &str) -> Vec<OwnedLogRecord> {
+        self.ring.lock().unwrap().records.iter()
+            .filter(|record| level >= record.level() && record.target.starts_with(target))
+            .cloned()
+            .collect()
+    }
+
+    /// Serialize the stored records into a JSON array.
+    pub fn to_json(&self) -> String {
+        json::encode(&self.snapshot()).unwrap()
+    }
+
+    /// Serialize the stored records using a formatter.
+    pub fn format(&self, formatter: fn(&ExtendedLogRecord) -> String) -> String {
+        self.ring.lock().unwrap().records.iter().map(|record| formatter(&record.as_record())).collect()
+    }
+}
+
+/// Handler which keeps the most recent records in a ring.
+///
+/// The ring is bounded by a number of records, a size in bytes, or both. When it is full, the
+/// oldest records are dropped. Records are queried through the `RingBuffer` returned by
+/// `RingHandler::buffer`.
+///
+/// # Examples
+///
+/// Keep the last 500 records:
+///
+/// ```rust
+/// let mut hdlr = RingHandler::new(Some(500), None, Some(LogLevelFilter::Debug));
+/// let buffer = hdlr.buffer();
+///
+/// hdlr.handle(&rec);
+///
+/// println!("{}", buffer.format(json));
+/// ```
+pub struct RingHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The stored records.
+    buffer: RingBuffer,
+}
+
+impl RingHandler {
+    /// Create a new handler instance keeping at most `max_records` records of at most `max_bytes`
+    /// bytes. The ring is unbounded when both are `None`.
+    pub fn new(max_records: Option<usize>, max_bytes: Option<usize>, level: Option<LogLevelFilter>) -> RingHandler {
+        RingHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            buffer: RingBuffer {
+                ring: Arc::new(Mutex::new(Ring {
+                    records: VecDeque::new(),
+                    bytes: 0,
+                    max_records,
+                    max_bytes,
+                })),
+            },
+        }
+    }
+
+    /// Shared access to the stored records.
+    pub fn buffer(&self) -> RingBuffer {
+        self.buffer.clone()
+    }
+}
+
+impl Filter for RingHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for RingHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Store the record, dropping the oldest ones to make room for it.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let record = OwnedLogRecord::from(record);
+        let size = size_of(&record);
+        let mut ring = self.buffer.ring.lock().unwrap();
+        if ring.max_records == Some(0) || ring.max_bytes.map(|max| size > max).unwrap_or(false) {
+            return Ok(());
+        }
+        while !ring.has_room(size) {
+            if let Some(oldest) = ring.records.pop_front() {
+                ring.bytes -= size_of(&oldest);
+            }
+        }
+        ring.bytes += size;
+        ring.records.push_back(record);
+        Ok(())
+    }
+}
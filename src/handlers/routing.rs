@@ -0,0 +1,158 @@
+//!
+//! A handler to dispatch log records to other handlers according to routing rules.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::io;
+use ExtendedLogRecord;
+
+/// Condition a record must satisfy to follow a route.
+pub enum Rule {
+    /// The record target starts with the prefix.
+    Target(String),
+    /// The record was logged from the module or one of its submodules.
+    Module(String),
+    /// The record level is between the two levels, inclusive (e.g. `Levels(LogLevel::Error, LogLevel::Warn)`).
+    Levels(LogLevel, LogLevel),
+    /// The callback accepts the record.
+    Predicate(fn(&ExtendedLogRecord) -> bool),
+    /// The record satisfies every rule.
+    All(Vec<Rule>),
+}
+
+impl Rule {
+    /// Determines if the record satisfies the rule.
+    pub fn matches(&self, record: &ExtendedLogRecord) -> bool {
+        match *self {
+            Rule::Target(ref prefix) => record.target.starts_with(prefix.as_str()),
+            Rule::Module(ref module) => {
+                record.module.starts_with(module.as_str())
+                    && (record.module.len() == module.len() || record.module[module.len()..].starts_with("::"))
+            }
+            Rule::Levels(from, to) => record.level() >= from && record.level() <= to,
+            Rule::Predicate(predicate) => predicate(record),
+            Rule::All(ref rules) => rules.iter().all(|rule| rule.matches(record)),
+        }
+    }
+}
+
+/// A routing rule and the handlers which receive the matching records.
+pub struct Route {
+    /// Condition to follow the route.
+    pub rule: Rule,
+    /// The handlers which receive the matching records.
+    pub handlers: Vec<Handler>,
+    /// Stop the routing when the route matches, otherwise the next routes are evaluated too.
+    pub last: bool,
+}
+
+impl Route {
+    /// Create a route which stops the routing when it matches.
+    pub fn new(rule: Rule, handlers: Vec<Handler>) -> Route {
+        Route { rule, handlers, last: true }
+    }
+}
+
+/// Handler which sends each record to the handlers of the routes it matches.
+///
+/// Routes are evaluated in order; a matching route stops the evaluation unless its `last` flag is
+/// unset. Records which match no route are sent to the `fallback` handlers.
+///
+/// # Examples
+///
+/// Send the records of the `db` module to their own file, copy the errors of the other modules to
+/// stderr and write everything else to stdout:
+///
+/// ```rust
+/// let mut errors = Route::new(
+///     Rule::Levels(LogLevel::Error, LogLevel::Error),
+///     vec![Handler::from(StderrHandler::new(Some(LogLevelFilter::Error), Some(text)))],
+/// );
+/// errors.last = false;
+///
+/// let mut hdlr = RoutingHandler::new(
+///     vec![
+///         Route::new(
+///             Rule::Module(String::from("app::db")),
+///             vec![Handler::from(FileHandler::new("/tmp/db.txt", Some(LogLevelFilter::Debug), Some(json)))],
+///         ),
+///         errors,
+///     ],
+///     vec![Handler::from(StdoutHandler::new(Some(LogLevelFilter::Info), Some(text)))],
+///     Some(LogLevelFilter::Debug),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct RoutingHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The ordered routes.
+    pub routes: Vec<Route>,
+    /// The handlers which receive the records matching no route.
+    pub fallback: Vec<Handler>,
+}
+
+impl RoutingHandler {
+    /// Create a new handler instance.
+    pub fn new(routes: Vec<Route>, fallback: Vec<Handler>, level: Option<LogLevelFilter>) -> RoutingHandler {
+        RoutingHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            routes,
+            fallback,
+        }
+    }
+}
+
+impl Filter for RoutingHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for RoutingHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the record to the handlers of the matching routes, or to the fallback handlers.
+    ///
+    /// Every handler receives the record even if another one fails, the first error is returned.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let mut result = Ok(());
+        let mut matched = false;
+        for route in self.routes.iter_mut() {
+            if !route.rule.matches(record) {
+                continue;
+            }
+            matched = true;
+            for hdlr in route.handlers.iter_mut() {
+                let emitted = hdlr.handle(record);
+                if result.is_ok() {
+                    result = emitted;
+                }
+            }
+            if route.last {
+                break;
+            }
+        }
+        if !matched {
+            for hdlr in self.fallback.iter_mut() {
+                let emitted = hdlr.handle(record);
+                if result.is_ok() {
+                    result = emitted;
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,148 @@
+//!
+//! A handler to keep a fraction of the log records of each level.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::io;
+use time;
+use ExtendedLogRecord;
+
+/// Resolution of the sample rates.
+const PRECISION: u64 = 1000000;
+
+/// Bucket of a sampling key, in `0..1000000`: records with this key are kept at the rates above
+/// `bucket / 1000000`.
+///
+/// The key is hashed with 64-bit FNV-1a, so a key falls in the same bucket across Rust releases
+/// and platforms.
+pub fn bucket(key: &str) -> u64 {
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash % PRECISION
+}
+
+/// Handler which sends a fraction of the records of each level to `target`.
+///
+/// The rate of a level is the fraction of its records which are kept, from `0.0` to `1.0`
+/// (the default). Records are picked at random, unless a `key` is given: the records are then
+/// picked by the `bucket` of their key, so all the records sharing a key (e.g. a request
+/// identifier found in the message) are kept or dropped together, by every process. A key kept
+/// at a rate is also kept at any higher rate.
+///
+/// The random generator is seeded from the current time, `set_seed` makes the picks
+/// reproducible.
+///
+/// The `sample_rate` of the sampled records is set to the rate of their level, so that downstream
+/// counts can be scaled back up: the `json` and `logfmt` formatters render it, a `Pattern` with
+/// `{sample_rate}`. Records of levels with a rate of `1.0` are left unchanged.
+///
+/// # Examples
+///
+/// Keep all the errors and warnings, 10% of the info and 1% of the debug records:
+///
+/// ```rust
+/// let mut hdlr = SamplingHandler::new(
+///     Handler::from(StdoutHandler::new(Some(LogLevelFilter::Debug), None)),
+///     Some(LogLevelFilter::Debug),
+/// );
+/// hdlr.set_rate(LogLevel::Info, 0.1);
+/// hdlr.set_rate(LogLevel::Debug, 0.01);
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct SamplingHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The handler which receives the sampled records.
+    pub target: Box<Handler>,
+    /// Callback returning the key of a record, to sample related records together.
+    pub key: Option<fn(&ExtendedLogRecord) -> String>,
+    /// Sample rates, from `Error` to `Trace`.
+    rates: [f64; 5],
+    /// State of the pseudo-random generator.
+    seed: u64,
+}
+
+impl SamplingHandler {
+    /// Create a new handler instance keeping all the records.
+    pub fn new(target: Handler, level: Option<LogLevelFilter>) -> SamplingHandler {
+        let now = time::get_time();
+        SamplingHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            target: Box::new(target),
+            key: None,
+            rates: [1.0; 5],
+            seed: ((now.sec as u64) ^ ((now.nsec as u64) << 32)) | 1,
+        }
+    }
+
+    /// Sample rate of a level.
+    pub fn rate(&self, level: LogLevel) -> f64 {
+        self.rates[level as usize - 1]
+    }
+
+    /// Set the sample rate of a level, clamped between `0.0` and `1.0`.
+    pub fn set_rate(&mut self, level: LogLevel, rate: f64) {
+        self.rates[level as usize - 1] = rate.clamp(0.0, 1.0);
+    }
+
+    /// Seed the random generator, the same seed picks the same records.
+    pub fn set_seed(&mut self, seed: u64) {
+        // Xorshift never leaves a zero state.
+        self.seed = seed.max(1);
+    }
+
+    /// Pick a number in `0..PRECISION`, from the record key or at random (xorshift).
+    fn pick(&mut self, record: &ExtendedLogRecord) -> u64 {
+        match self.key {
+            Some(key) => bucket(&key(record)),
+            None => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 7;
+                self.seed ^= self.seed << 17;
+                self.seed % PRECISION
+            }
+        }
+    }
+}
+
+impl Filter for SamplingHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for SamplingHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the record to the target handler if it is picked, with its sample rate.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let rate = self.rate(record.level());
+        if rate >= 1.0 {
+            return self.target.handle(record);
+        }
+        if self.pick(record) as f64 >= rate * PRECISION as f64 {
+            return Ok(());
+        }
+        self.target.handle(&ExtendedLogRecord {
+            date: record.date.clone(),
+            level: record.level.clone(),
+            msg: record.msg.clone(),
+            target: record.target.clone(),
+            sample_rate: Some(rate),
+            ..*record
+        })
+    }
+}
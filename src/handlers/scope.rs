@@ -0,0 +1,384 @@
+//!
+//! A handler to keep the whole trace of failed requests only.
+//!
+
+use handlers::{Handle, Handler, Filter};
+use log::{LogLevel, LogLevelFilter};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use {ExtendedLogRecord, OwnedLogRecord};
+
+/// Identifier of the next `ScopeHandler`.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The live `ScopeHandler`s by identifier, which receive the records of the closed scopes.
+static SINKS: Mutex<BTreeMap<usize, Weak<Mutex<Sink>>>> = Mutex::new(BTreeMap::new());
+
+/// Held back records, with the identifier of the `ScopeHandler` holding them.
+type Records = Vec<(usize, OwnedLogRecord)>;
+
+/// State of a scope, shared by its `RequestScope` and the handlers.
+struct State {
+    /// Set by `RequestScope::fail` or when a record at or above the flush level is handled.
+    failed: bool,
+    /// Set when the `RequestScope` is dropped.
+    closed: bool,
+    /// The scope which was current when this one was opened.
+    parent: Option<Scope>,
+    /// The held back records.
+    records: Records,
+}
+
+/// Reference to the state of a request scope.
+///
+/// It lets records handled by another thread be held back by the scope which was current when
+/// they were logged, see `current` and `enter`.
+#[derive(Clone)]
+pub struct Scope {
+    state: Arc<Mutex<State>>,
+}
+
+impl Scope {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn is(&self, other: &Scope) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+
+    /// Hold back records in the scope, or in the outer scope which receives them once the scope
+    /// closed without failure. `fail` marks the receiving scope as failed.
+    ///
+    /// Returns the receiving scope, or the records along with the failure of the last scope if it
+    /// is already closed.
+    fn hold(&self, records: Records, fail: bool) -> Result<Scope, (Records, bool)> {
+        let mut scope = self.clone();
+        loop {
+            let parent = {
+                let mut state = scope.lock();
+                if !state.closed {
+                    state.failed |= fail;
+                    state.records.extend(records);
+                    break;
+                }
+                match state.parent {
+                    Some(ref parent) if !state.failed => parent.clone(),
+                    _ => return Err((records, state.failed || fail)),
+                }
+            };
+            scope = parent;
+        }
+        Ok(scope)
+    }
+
+    /// The open scope which receives the records held back by this one, if any.
+    fn receiver(&self) -> Option<Scope> {
+        let parent = {
+            let state = self.lock();
+            if !state.closed {
+                return Some(self.clone());
+            }
+            if state.failed { None } else { state.parent.clone() }
+        };
+        parent.and_then(|parent| parent.receiver())
+    }
+}
+
+thread_local! {
+    /// Scopes current on this thread, innermost last. `None` masks the outer scopes.
+    static SCOPES: RefCell<Vec<Option<Scope>>> = const { RefCell::new(vec![]) };
+}
+
+/// The innermost scope of the current thread.
+pub fn current() -> Option<Scope> {
+    SCOPES.with(|scopes| scopes.borrow().last().cloned().and_then(|scope| scope))
+}
+
+/// Run `f` with `scope`, usually taken by `current` on another thread, as the current scope.
+pub fn enter<F, R>(scope: Option<Scope>, f: F) -> R
+    where F: FnOnce() -> R
+{
+    SCOPES.with(|scopes| scopes.borrow_mut().push(scope));
+    let result = f();
+    SCOPES.with(|scopes| scopes.borrow_mut().pop());
+    result
+}
+
+/// Send the records of a closed scope through the `ScopeHandler`s holding them: all of them if
+/// the scope failed, the ones at or above their `base_level` otherwise.
+///
+/// The records of the handlers dropped meanwhile are lost.
+fn release(records: Records, failed: bool) -> io::Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let mut grouped: BTreeMap<usize, Vec<OwnedLogRecord>> = BTreeMap::new();
+    for (id, record) in records {
+        grouped.entry(id).or_default().push(record);
+    }
+    let sinks: Vec<(Arc<Mutex<Sink>>, Vec<OwnedLogRecord>)> = {
+        let sinks = SINKS.lock().unwrap();
+        grouped.into_iter()
+            .filter_map(|(id, records)| sinks.get(&id).and_then(Weak::upgrade).map(|sink| (sink, records)))
+            .collect()
+    };
+    let mut result = Ok(());
+    for (sink, records) in sinks {
+        let sent = sink.lock().unwrap().send(records, failed);
+        result = result.and(sent);
+    }
+    result
+}
+
+/// Guard of a request scope opened by the current thread.
+///
+/// The records handled by the `ScopeHandler`s while the guard is alive are held back in the
+/// scope. When the guard is dropped, the scope is closed: if it did not fail and was opened
+/// within another scope, its records are handed to that outer scope, which decides their fate.
+/// Otherwise they are sent right away by the `ScopeHandler`s which hold them; `close` does the
+/// same and reports the errors of the handlers.
+///
+/// # Examples
+///
+/// ```rust
+/// fn serve(request: Request) -> Response {
+///     let scope = RequestScope::open();
+///     debug!("parsing {:?}", request);
+///     let response = match process(request) {
+///         Ok(response) => response,
+///         Err(err) => {
+///             scope.fail();
+///             Response::error(err)
+///         }
+///     };
+///     let _ = scope.close();
+///     response
+/// }
+/// ```
+pub struct RequestScope {
+    scope: Scope,
+}
+
+impl RequestScope {
+    /// Open a new scope on the current thread.
+    pub fn open() -> RequestScope {
+        let scope = Scope {
+            state: Arc::new(Mutex::new(State { failed: false, closed: false, parent: current(), records: vec![] })),
+        };
+        SCOPES.with(|scopes| scopes.borrow_mut().push(Some(scope.clone())));
+        RequestScope { scope }
+    }
+
+    /// Mark the scope as failed: all its records will be kept.
+    pub fn fail(&self) {
+        self.scope.lock().failed = true;
+    }
+
+    /// Determines if the scope failed.
+    pub fn failed(&self) -> bool {
+        self.scope.lock().failed
+    }
+
+    /// Close the scope, returning the first error of the handlers which sent its records.
+    pub fn close(mut self) -> io::Result<()> {
+        self.finish()
+    }
+
+    /// Close the scope and hand its records to the outer scope, or send them.
+    fn finish(&mut self) -> io::Result<()> {
+        SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            if let Some(idx) = scopes.iter().rposition(|scope| scope.as_ref().map(|scope| scope.is(&self.scope)).unwrap_or(false)) {
+                scopes.remove(idx);
+            }
+        });
+        let (records, failed, parent) = {
+            let mut state = self.scope.lock();
+            if state.closed {
+                return Ok(());
+            }
+            state.closed = true;
+            (mem::take(&mut state.records), state.failed, state.parent.clone())
+        };
+        match parent {
+            Some(ref parent) if !failed => match parent.hold(records, false) {
+                Ok(_) => Ok(()),
+                Err((records, failed)) => release(records, failed),
+            },
+            _ => release(records, failed),
+        }
+    }
+}
+
+impl Drop for RequestScope {
+    /// Close the scope, see `close`.
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Part of a `ScopeHandler` which sends the records, shared with the closing scopes.
+struct Sink {
+    /// Records kept when the scope didn't fail.
+    base_level: LogLevelFilter,
+    /// The handler which receives the kept records.
+    target: Box<Handler>,
+}
+
+impl Sink {
+    /// Send the records released by a scope, every record is sent even if the target handler
+    /// fails, the first error is returned.
+    fn send(&mut self, records: Vec<OwnedLogRecord>, failed: bool) -> io::Result<()> {
+        let base_level = self.base_level;
+        let mut result = Ok(());
+        for record in records.into_iter().filter(|record| failed || base_level >= record.level()) {
+            let sent = self.target.handle(&record.as_record());
+            result = result.and(sent);
+        }
+        result
+    }
+}
+
+/// Handler which holds back the records logged within a `RequestScope` until the scope is closed.
+///
+/// If the scope failed, because it was marked with `RequestScope::fail` or a record at or above
+/// `flush_level` was logged within it, all its records are sent to `target`. Otherwise only the
+/// records at or above `base_level` are sent. Records logged outside of a scope are sent right
+/// away if they are at or above `base_level`.
+///
+/// The records are sent by the thread which closes the scope, without locking the logger. A
+/// `QueueHandler` passes the current scope to its worker, so the handler may be wrapped in it:
+/// the records which reach the handler once their scope is closed are sent right away.
+///
+/// # Examples
+///
+/// Keep the DEBUG records of the failed requests only:
+///
+/// ```rust
+/// ExtendedLogger::add_scope_handler(
+///     Handler::from(StdoutHandler::new(Some(LogLevelFilter::Debug), None)),
+///     LogLevelFilter::Info,
+///     Some(LogLevelFilter::Debug),
+/// );
+/// ```
+pub struct ScopeHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Records at or above this level make the scope fail.
+    pub flush_level: LogLevel,
+    /// Identifier of the handler in the scopes.
+    id: usize,
+    /// The base level and the target handler.
+    sink: Arc<Mutex<Sink>>,
+    /// The scopes holding back records of the handler.
+    scopes: Vec<Scope>,
+}
+
+impl ScopeHandler {
+    /// Create a new handler instance which sends the kept records to `target`.
+    pub fn new(target: Handler, base_level: LogLevelFilter, level: Option<LogLevelFilter>) -> ScopeHandler {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let sink = Arc::new(Mutex::new(Sink { base_level, target: Box::new(target) }));
+        SINKS.lock().unwrap().insert(id, Arc::downgrade(&sink));
+        ScopeHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            flush_level: LogLevel::Error,
+            id,
+            sink,
+            scopes: vec![],
+        }
+    }
+
+    /// Number of records held back, for all scopes.
+    pub fn len(&self) -> usize {
+        self.receivers().iter().map(|scope| scope.lock().records.iter().filter(|&&(id, _)| id == self.id).count()).sum()
+    }
+
+    /// Determines if no record is held back.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The open scopes which now hold the records of the tracked scopes.
+    fn receivers(&self) -> Vec<Scope> {
+        let mut scopes: Vec<Scope> = vec![];
+        for scope in self.scopes.iter().filter_map(Scope::receiver) {
+            if !scopes.iter().any(|tracked| tracked.is(&scope)) {
+                scopes.push(scope);
+            }
+        }
+        scopes
+    }
+
+    /// Remember that a scope holds back records of the handler, and forget the closed ones.
+    fn track(&mut self, scope: Scope) {
+        let mut scopes = self.receivers();
+        if !scopes.iter().any(|tracked| tracked.is(&scope)) {
+            scopes.push(scope);
+        }
+        self.scopes = scopes;
+    }
+}
+
+impl Drop for ScopeHandler {
+    /// Send the records held back by the open scopes, according to their current state.
+    fn drop(&mut self) {
+        SINKS.lock().unwrap().remove(&self.id);
+        for scope in self.receivers() {
+            let (records, failed) = {
+                let mut state = scope.lock();
+                let (mine, others): (Records, Records) = mem::take(&mut state.records).into_iter().partition(|&(id, _)| id == self.id);
+                state.records = others;
+                (mine, state.failed)
+            };
+            let records = records.into_iter().map(|(_, record)| record).collect();
+            let _ = self.sink.lock().unwrap().send(records, failed);
+        }
+    }
+}
+
+impl Filter for ScopeHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for ScopeHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Hold back the record if a scope is current, otherwise send it if it is at or above
+    /// `base_level`.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let scope = match current() {
+            Some(scope) => scope,
+            None => return self.sink.lock().unwrap().send(vec![OwnedLogRecord::from(record)], false),
+        };
+        let fail = record.level() <= self.flush_level;
+        match scope.hold(vec![(self.id, OwnedLogRecord::from(record))], fail) {
+            Ok(scope) => {
+                self.track(scope);
+                Ok(())
+            }
+            // The record was handled after its scope was closed, e.g. by a queue.
+            Err((records, failed)) => {
+                let records = records.into_iter().map(|(_, record)| record).collect();
+                self.sink.lock().unwrap().send(records, failed)
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//!
+//! A handler to count log records with a StatsD agent.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use ExtendedLogRecord;
+
+/// Replace the characters which would break a DogStatsD tag.
+fn sanitize(tag: &str) -> String {
+    tag.replace(['|', ',', '#', '\n', '\r'], "_")
+}
+
+/// Handler which sends a StatsD counter increment for each record to an agent over UDP.
+///
+/// With `dogstatsd` (the default), the level and target are sent as DogStatsD tags along with
+/// the static `tags`: `logs.count:1|c|#level:error,target:db,env:prod`. Otherwise, the level is
+/// appended to the metric name for plain StatsD agents: `logs.count.error:1|c`.
+///
+/// Each record is sent in its own datagram, a lost datagram is a lost increment.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut hdlr = StatsdHandler::new("127.0.0.1:8125", "myapp.logs", Some(LogLevelFilter::Info)).unwrap();
+/// hdlr.tags.push(String::from("env:prod"));
+///
+/// hdlr.handle(&rec);
+/// ```
+pub struct StatsdHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Name of the counter.
+    pub metric: String,
+    /// Tags added to every increment, like `env:prod`.
+    pub tags: Vec<String>,
+    /// Send the level and target as DogStatsD tags.
+    pub dogstatsd: bool,
+    /// The socket used to send the datagrams.
+    socket: UdpSocket,
+    /// The address of the agent.
+    address: SocketAddr,
+}
+
+impl StatsdHandler {
+    /// Create a new handler instance sending the increments of `metric` to the agent at `address`.
+    ///
+    /// Fails if `address` doesn't resolve or no local socket can be bound.
+    pub fn new(address: &str, metric: &str, level: Option<LogLevelFilter>) -> io::Result<StatsdHandler> {
+        let address = match address.to_socket_addrs()?.next() {
+            Some(address) => address,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: no address found", address))),
+        };
+        let local = if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        Ok(StatsdHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            metric: String::from(metric),
+            tags: vec![],
+            dogstatsd: true,
+            socket: UdpSocket::bind(local)?,
+            address,
+        })
+    }
+
+    /// Build the datagram counting a record.
+    fn datagram(&self, record: &ExtendedLogRecord) -> String {
+        let level = record.level.to_lowercase();
+        if !self.dogstatsd {
+            return format!("{}.{}:1|c", self.metric, level);
+        }
+        let mut tags = vec![format!("level:{}", level), format!("target:{}", sanitize(&record.target))];
+        tags.extend(self.tags.iter().map(|tag| sanitize(tag)));
+        format!("{}:1|c|#{}", self.metric, tags.join(","))
+    }
+}
+
+impl Filter for StatsdHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for StatsdHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the counter increment of the record.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        self.socket.send_to(self.datagram(record).as_bytes(), self.address).map(|_| ())
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Console helpers which pick the formatter depending on the terminal capabilities.
+//!
+
+use formatter::{colored, text};
+use handlers::streams::stderr::StderrHandler;
+use handlers::streams::stdout::StdoutHandler;
+use handlers::streams::writer::WriterHandler;
+use log::LogLevelFilter;
+use ExtendedLogRecord;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{IsTerminal, Write};
+
+/// Determines if colors should be written to a stream, `terminal` telling if it is a terminal.
+///
+/// `NO_COLOR` disables colors and `CLICOLOR_FORCE` enables them even when the stream is not a
+/// terminal. Otherwise colors are used only if the stream is a terminal.
+pub fn colors_enabled(terminal: bool) -> bool {
+    colors_for(env::var_os("NO_COLOR").as_deref(), env::var_os("CLICOLOR_FORCE").as_deref(), terminal)
+}
+
+/// Determines if colors should be written to a stream given the values of `NO_COLOR` and
+/// `CLICOLOR_FORCE`, see `colors_enabled`.
+pub fn colors_for(no_color: Option<&OsStr>, clicolor_force: Option<&OsStr>, terminal: bool) -> bool {
+    if no_color.map(|value| !value.is_empty()).unwrap_or(false) {
+        return false;
+    }
+    if clicolor_force.map(|value| !value.is_empty() && value != "0").unwrap_or(false) {
+        return true;
+    }
+    terminal
+}
+
+/// Determines if colors should be written to the stream, see `colors_enabled`.
+pub fn use_colors<S: IsTerminal>(stream: &S) -> bool {
+    colors_enabled(stream.is_terminal())
+}
+
+impl StdoutHandler {
+    /// Create a stdout handler which formats records with `color_formatter` when colors are
+    /// enabled and with `formatter` otherwise.
+    ///
+    /// Defaults to `formatter::colored` and `formatter::text`.
+    pub fn console(level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>, color_formatter: Option<fn(&ExtendedLogRecord) -> String>) -> StdoutHandler {
+        let mut hdlr = StdoutHandler::new(level, formatter.or(Some(text)));
+        if use_colors(&hdlr.stream) {
+            hdlr.formatter = color_formatter.unwrap_or(colored);
+        }
+        hdlr
+    }
+}
+
+impl StderrHandler {
+    /// Create a stderr handler which formats records with `color_formatter` when colors are
+    /// enabled and with `formatter` otherwise.
+    ///
+    /// Defaults to `formatter::colored` and `formatter::text`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut hdlr = StderrHandler::console(Some(LogLevelFilter::Info), None, None);
+    ///
+    /// hdlr.handle(&rec);
+    /// ```
+    ///
+    /// On a terminal, the level is colored:
+    ///
+    /// ```
+    /// 2017-04-24T14:45:47Z \x1b[1;31mERROR\x1b[0m log_handlers::tests: oh no !
+    /// ```
+    pub fn console(level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>, color_formatter: Option<fn(&ExtendedLogRecord) -> String>) -> StderrHandler {
+        let mut hdlr = StderrHandler::new(level, formatter.or(Some(text)));
+        if use_colors(&hdlr.stream) {
+            hdlr.formatter = color_formatter.unwrap_or(colored);
+        }
+        hdlr
+    }
+}
+
+impl WriterHandler {
+    /// Create a writer handler which formats records with `color_formatter` when `colors` is set
+    /// and with `formatter` otherwise. Use `colors_enabled` to follow the environment.
+    ///
+    /// Defaults to `formatter::colored` and `formatter::text`.
+    pub fn console(writer: Box<dyn Write + Send>, colors: bool, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>, color_formatter: Option<fn(&ExtendedLogRecord) -> String>) -> WriterHandler {
+        let mut hdlr = WriterHandler::new(writer, level, formatter.or(Some(text)));
+        if colors {
+            hdlr.formatter = color_formatter.unwrap_or(colored);
+        }
+        hdlr
+    }
+}
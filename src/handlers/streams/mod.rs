@@ -2,14 +2,17 @@
 //! A set of stream based handlers such as file, stdout ...
 //!
 
+pub mod console;
 pub mod file;
+pub mod stderr;
 pub mod stdout;
 pub mod net;
+pub mod writer;
 
 use handlers::{Handle, Filter};
 use log::LogLevelFilter;
 use ExtendedLogRecord;
-use std::io::Write;
+use std::io::{self, Write};
 
 /// Base handler for streams
 ///
@@ -42,13 +45,15 @@ impl<W> Filter for StreamHandler<W> where W: Write {
 
 impl<W> Handle for StreamHandler<W> where W: Write {
     /// Check if the log record may be emitted. `self.level` and filters will be checked.
-    fn handle(&mut self, record: &ExtendedLogRecord) {
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
         if self.level >= record.level() && self.filter(record) == true {
             self.emit(record)
+        } else {
+            Ok(())
         }
     }
     /// Format the record into the stream using the formatter.
-    fn emit(&mut self, record: &ExtendedLogRecord) {
-        self.stream.write_all((self.formatter)(record).as_bytes()).unwrap();
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        self.stream.write_all((self.formatter)(record).as_bytes())
     }
 }
\ No newline at end of file
@@ -2,9 +2,82 @@ use formatter::default;
 use handlers::streams::StreamHandler;
 use log::LogLevelFilter;
 use ExtendedLogRecord;
+use std::io::{self, Write};
 use std::net::TcpStream;
 
-/// Type based on StreamHandler to handle the `TcpStream` stream.
+/// TCP connection to a server, re-established when it is lost.
+///
+/// The connection is opened on the first write. Before each write, it is checked that the server
+/// did not close it; after a closed connection or a write error, the next write reconnects. A
+/// write fails while the server cannot be reached.
+pub struct TcpConnection {
+    /// Address of the server.
+    address: String,
+    /// The current connection, if any.
+    stream: Option<TcpStream>,
+}
+
+impl TcpConnection {
+    /// Create a connection to `address`, opened on the first write.
+    pub fn new(address: &str) -> TcpConnection {
+        TcpConnection { address: String::from(address), stream: None }
+    }
+
+    /// Determines if a connection to the server is open.
+    pub fn connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// The open connection, reconnecting if the server closed it.
+    fn stream(&mut self) -> io::Result<&mut TcpStream> {
+        if self.stream.as_ref().map(closed).unwrap_or(false) {
+            self.stream = None;
+        }
+        if self.stream.is_none() {
+            self.stream = Some(TcpStream::connect(self.address.as_str())?);
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+}
+
+/// Determines if the server closed the connection, without blocking.
+fn closed(stream: &TcpStream) -> bool {
+    let mut buf = [0; 1];
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let closed = match stream.peek(&mut buf) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(ref err) => err.kind() != io::ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_err() || closed
+}
+
+impl Write for TcpConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.stream()?.write(buf);
+        if written.is_err() {
+            self.stream = None;
+        }
+        written
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        let flushed = match self.stream {
+            Some(ref mut stream) => stream.flush(),
+            None => Ok(()),
+        };
+        if flushed.is_err() {
+            self.stream = None;
+        }
+        flushed
+    }
+}
+
+/// Type based on StreamHandler to handle a `TcpConnection` stream.
+///
+/// The connection is opened by the first record and re-established by the next record when it is
+/// lost, so a `FailoverHandler` can switch back to it once the server is up again.
 ///
 /// # Examples
 ///
@@ -34,15 +107,15 @@ use std::net::TcpStream;
 /// ```json
 ///{"level":"INFO","levelno":3,"msg":"Test","target":"MyFactory","timestamp":1493042710,"module":"log_handlers::tests","file":"src/tests.rs","line":24,"date":"2017-04-24T14:05:10Z"}
 /// ```
-pub type TCPHandler = StreamHandler<TcpStream>;
+pub type TCPHandler = StreamHandler<TcpConnection>;
 
 impl TCPHandler {
-    pub fn new(address: &'static str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> TCPHandler {
+    pub fn new(address: &str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> TCPHandler {
         TCPHandler {
             filters: vec![],
             formatter: formatter.unwrap_or(default),
             level: level.unwrap_or(LogLevelFilter::Off),
-            stream: TcpStream::connect(address).unwrap(),
+            stream: TcpConnection::new(address),
         }
     }
 }
\ No newline at end of file
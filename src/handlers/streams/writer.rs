@@ -0,0 +1,49 @@
+use formatter::default;
+use handlers::streams::StreamHandler;
+use log::LogLevelFilter;
+use ExtendedLogRecord;
+use std::io::Write;
+
+/// Type based on StreamHandler to handle any `Write` stream, such as a `Vec<u8>`, a `BufWriter`,
+/// a pipe or a compression encoder.
+///
+/// # Examples
+///
+/// Manual test the handler:
+///
+/// ```rust
+///
+/// let rec = ExtendedLogRecord::new(
+///     file!(),
+///     LogLevel::Info,
+///     line!(),
+///     module_path!(),
+///     "Test".to_string(),
+///     "MyFactory".to_string()
+/// );
+/// let mut hdlr = WriterHandler::new(
+///     Box::new(BufWriter::new(File::create("/tmp/log.txt").unwrap())),
+///     Some(LogLevelFilter::Info),
+///     Some(json),
+/// );
+///
+/// hdlr.handle(&rec);
+/// ```
+///
+/// It will format the log record as JSON and write it into the buffered file:
+///
+/// ```json
+///{"level":"INFO","levelno":3,"msg":"Test","target":"MyFactory","timestamp":1493042710,"module":"log_handlers::tests","file":"src/tests.rs","line":24,"date":"2017-04-24T14:05:10Z"}
+/// ```
+pub type WriterHandler = StreamHandler<Box<dyn Write + Send>>;
+
+impl WriterHandler {
+    pub fn new(writer: Box<dyn Write + Send>, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> WriterHandler {
+        WriterHandler {
+            filters: vec![],
+            formatter: formatter.unwrap_or(default),
+            stream: writer,
+            level: level.unwrap_or(LogLevelFilter::Off)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//!
+//! A handler to stream log records to the clients connected on a local TCP port.
+//!
+
+use formatter::default;
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ExtendedLogRecord;
+
+/// Delay given to a client to send its handshake, in milliseconds.
+const HANDSHAKE_TIMEOUT: u64 = 500;
+
+/// A client connected to the tail server.
+struct TailClient {
+    /// The client socket, in non-blocking mode.
+    stream: TcpStream,
+    /// The maximum log level requested by the client.
+    level: LogLevelFilter,
+    /// Only the records which target starts with this prefix are sent.
+    target: String,
+}
+
+impl TailClient {
+    /// Determines if the record was requested by the client.
+    fn accept(&self, record: &ExtendedLogRecord) -> bool {
+        self.level >= record.level() && record.target.starts_with(self.target.as_str())
+    }
+}
+
+/// Handler which streams the formatted records to every connected client.
+///
+/// A client may start by sending a single line: the maximum log level it wants, optionally
+/// followed by a target prefix (e.g. `DEBUG db::pool`). A client which sends nothing within half
+/// a second, or an empty line, receives every record. Records are then streamed until the client
+/// disconnects.
+///
+/// Writes never block: a client which does not read fast enough to keep its socket buffer
+/// available is disconnected.
+///
+/// # Examples
+///
+/// Start the server:
+///
+/// ```rust
+/// let mut hdlr = TailHandler::new(
+///     "127.0.0.1:9999",
+///     Some(LogLevelFilter::Debug),
+///     Some(json),
+/// ).unwrap();
+///
+/// hdlr.handle(&rec);
+/// ```
+///
+/// Then follow every record:
+///
+/// ```sh
+/// $ nc 127.0.0.1 9999
+/// ```
+///
+/// Or only the warnings of the `db` module:
+///
+/// ```sh
+/// $ echo "WARN db" | nc 127.0.0.1 9999
+/// ```
+pub struct TailHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// Callback to format log record.
+    pub formatter: fn(&ExtendedLogRecord) -> String,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// Clients which completed their handshake.
+    clients: Arc<Mutex<Vec<TailClient>>>,
+}
+
+impl TailHandler {
+    /// Create a new handler instance and start listening on `address`.
+    ///
+    /// Fails if the server cannot listen on `address`, e.g. when the port is already in use.
+    pub fn new(address: &str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> io::Result<TailHandler> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(vec![]));
+        let registry = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let registry = registry.clone();
+                thread::spawn(move || handshake(stream, registry));
+            }
+        });
+        Ok(TailHandler {
+            filters: vec![],
+            formatter: formatter.unwrap_or(default),
+            level: level.unwrap_or(LogLevelFilter::Off),
+            address,
+            clients,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+}
+
+/// Read the handshake of a new client, if it sends one, and register it.
+fn handshake(stream: TcpStream, registry: Arc<Mutex<Vec<TailClient>>>) {
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(HANDSHAKE_TIMEOUT)));
+    let mut line = String::new();
+    // Without handshake, the client receives every record.
+    let _ = BufReader::new(&stream).read_line(&mut line);
+    let mut words = line.split_whitespace();
+    let level = match words.next() {
+        Some(level) => match LogLevelFilter::from_str(level) {
+            Ok(level) => level,
+            Err(_) => {
+                let _ = writeln!(&stream, "invalid level {}", level);
+                return;
+            }
+        },
+        None => LogLevelFilter::Trace,
+    };
+    let target = String::from(words.next().unwrap_or(""));
+    if stream.set_nonblocking(true).is_ok() {
+        registry.lock().unwrap().push(TailClient { stream, level, target });
+    }
+}
+
+impl Filter for TailHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for TailHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Send the formatted record to the clients which requested it, dropping the slow ones.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return Ok(());
+        }
+        let line = (self.formatter)(record);
+        clients.retain(|client| {
+            !client.accept(record) || (&client.stream).write_all(line.as_bytes()).is_ok()
+        });
+        Ok(())
+    }
+}
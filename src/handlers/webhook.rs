@@ -0,0 +1,259 @@
+//!
+//! A handler to post log records as alerts to Slack or Mattermost compatible incoming webhooks.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use rustc_serialize::json::Json;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use ExtendedLogRecord;
+
+/// Default message template.
+pub const DEFAULT_TEMPLATE: &str = "[{level}] {target}: {msg}";
+
+/// Delivery state of the alerts of a target.
+struct AlertState {
+    /// Last time an alert was posted or failed to be.
+    attempted_at: Instant,
+    /// Text of the last posted alert.
+    text: String,
+    /// Number of alerts suppressed since the last posted alert.
+    suppressed: u32,
+}
+
+/// Handler which posts log records to an incoming webhook.
+///
+/// The message text is rendered from `template`, in which `{date}`, `{file}`, `{level}`,
+/// `{levelno}`, `{line}`, `{module}`, `{msg}`, `{target}` and `{timestamp}` are replaced by the
+/// matching `ExtendedLogRecord` field.
+///
+/// Alerts are rate limited per target: at most one alert is posted every `interval`, and an alert
+/// identical to the previous one of the same target is not posted again before `repeat_interval`.
+/// The next alert which goes through reports how many alerts were suppressed in between; once
+/// `interval` is over, the count is also posted on its own when another alert is emitted, or by
+/// `flush`. The counts still pending when the handler is dropped are lost.
+///
+/// An alert which cannot be posted is counted as suppressed, and the target waits `interval`
+/// before the next attempt, so an unreachable webhook is not retried for every record.
+///
+/// The alerts are posted by the logging thread, which waits for the webhook at most `timeout`
+/// to connect, send the payload and read the reply.
+///
+/// Only plain `http://` URLs are supported, HTTPS webhooks need a relay: `new` fails for any
+/// other scheme.
+///
+/// # Examples
+///
+/// Post errors from the `db` module to a Mattermost channel:
+///
+/// ```rust
+/// fn db_only(record: &ExtendedLogRecord) -> bool {
+///     record.target.starts_with("db")
+/// }
+///
+/// let mut hdlr = WebhookHandler::new(
+///     "http://mattermost.local/hooks/xxx-generatedkey-xxx",
+///     Some(LogLevelFilter::Error),
+///     Some("{date} - {target} - {msg}"),
+/// ).unwrap();
+/// hdlr.filters.push(db_only);
+/// hdlr.channel = Some("alerts".to_string());
+/// ```
+pub struct WebhookHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// Template used to render the message text.
+    pub template: String,
+    /// Overrides the channel configured for the webhook.
+    pub channel: Option<String>,
+    /// Overrides the user name configured for the webhook.
+    pub username: Option<String>,
+    /// Minimal delay between two alerts of the same target.
+    pub interval: Duration,
+    /// Minimal delay before an alert identical to the previous one of the same target is posted.
+    pub repeat_interval: Duration,
+    /// Maximum delay to connect to the webhook, and then to send the payload and read the reply.
+    pub timeout: Duration,
+    /// Address of the webhook, as `host:port`.
+    host: String,
+    /// Path of the webhook URL.
+    path: String,
+    /// Alert state by target.
+    alerts: HashMap<String, AlertState>,
+}
+
+impl WebhookHandler {
+    /// Create a new handler instance, fails if `url` is not an `http://` URL.
+    pub fn new(url: &str, level: Option<LogLevelFilter>, template: Option<&str>) -> io::Result<WebhookHandler> {
+        let (host, path) = split_url(url)?;
+        Ok(WebhookHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            template: String::from(template.unwrap_or(DEFAULT_TEMPLATE)),
+            channel: None,
+            username: None,
+            interval: Duration::from_secs(60),
+            repeat_interval: Duration::from_secs(3600),
+            timeout: Duration::from_secs(1),
+            host,
+            path,
+            alerts: HashMap::new(),
+        })
+    }
+
+    /// Build the JSON payload expected by the webhook.
+    fn payload(&self, text: String) -> String {
+        let mut payload = BTreeMap::new();
+        payload.insert(String::from("text"), Json::String(text));
+        if let Some(ref channel) = self.channel {
+            payload.insert(String::from("channel"), Json::String(channel.clone()));
+        }
+        if let Some(ref username) = self.username {
+            payload.insert(String::from("username"), Json::String(username.clone()));
+        }
+        Json::Object(payload).to_string()
+    }
+
+    /// Post the payload to the webhook URL.
+    fn post(&self, body: &str) -> io::Result<()> {
+        let timeout = self.timeout;
+        let addr = self.host.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("cannot resolve {}", self.host))
+        })?;
+        let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path, self.host, body.len(), body
+        )?;
+        let mut status = [0; 12];
+        stream.read_exact(&mut status)?;
+        match &status[9..10] {
+            b"2" => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("webhook replied {}", String::from_utf8_lossy(&status[9..]))
+            )),
+        }
+    }
+
+    /// Post an alert of a target along with the number of alerts suppressed before it.
+    ///
+    /// If the alert cannot be posted, it is counted as suppressed and only the time of the
+    /// attempt is recorded.
+    fn send(&mut self, target: &str, text: String, now: Instant) -> io::Result<()> {
+        let suppressed = self.alerts.get(target).map(|state| state.suppressed).unwrap_or(0);
+        let mut message = text.clone();
+        if suppressed > 0 {
+            message.push_str(&format!("\n_{} similar alert(s) suppressed_", suppressed));
+        }
+        let body = self.payload(message);
+        match self.post(&body) {
+            Ok(()) => {
+                self.alerts.insert(String::from(target), AlertState { attempted_at: now, text, suppressed: 0 });
+                Ok(())
+            }
+            Err(err) => {
+                let state = self.alerts.entry(String::from(target))
+                    .or_insert_with(|| AlertState { attempted_at: now, text: String::new(), suppressed: 0 });
+                state.attempted_at = now;
+                state.suppressed += 1;
+                Err(err)
+            }
+        }
+    }
+
+    /// Post the number of suppressed alerts of the targets which `interval` is over.
+    ///
+    /// Every count is posted even if the webhook fails, the first error is returned. A failed
+    /// count waits for `interval` again.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        let interval = self.interval;
+        let pending: Vec<(String, u32)> = self.alerts.iter()
+            .filter(|&(_, state)| state.suppressed > 0)
+            .filter(|&(_, state)| now.duration_since(state.attempted_at) >= interval)
+            .map(|(target, state)| (target.clone(), state.suppressed))
+            .collect();
+        let mut result = Ok(());
+        for (target, suppressed) in pending {
+            let body = self.payload(format!("_{} similar alert(s) from {} suppressed_", suppressed, target));
+            let sent = self.post(&body);
+            if let Some(state) = self.alerts.get_mut(&target) {
+                state.attempted_at = now;
+                if sent.is_ok() {
+                    state.suppressed = 0;
+                }
+            }
+            result = result.and(sent);
+        }
+        result
+    }
+}
+
+impl Filter for WebhookHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for WebhookHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Render the record and post it unless the target exceeded its rate limit. The pending
+    /// counts of suppressed alerts are then posted, unless the webhook just failed.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let text = render(&self.template, record);
+        let now = Instant::now();
+        if let Some(state) = self.alerts.get_mut(&record.target) {
+            let elapsed = now.duration_since(state.attempted_at);
+            if elapsed < self.interval || (text == state.text && elapsed < self.repeat_interval) {
+                state.suppressed += 1;
+                return self.flush();
+            }
+        }
+        self.send(&record.target, text, now).and_then(|()| self.flush())
+    }
+}
+
+/// Render a template using the record fields.
+fn render(template: &str, record: &ExtendedLogRecord) -> String {
+    template
+        .replace("{date}", &record.date)
+        .replace("{file}", record.file)
+        .replace("{level}", &record.level)
+        .replace("{levelno}", &record.levelno.to_string())
+        .replace("{line}", &record.line.to_string())
+        .replace("{module}", record.module)
+        .replace("{target}", &record.target)
+        .replace("{timestamp}", &record.timestamp.to_string())
+        .replace("{msg}", &record.msg)
+}
+
+/// Split an `http://host[:port]/path` URL into its address and path.
+fn split_url(url: &str) -> io::Result<(String, String)> {
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported URL {}", url))),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+    let host = if host.contains(':') { String::from(host) } else { format!("{}:80", host) };
+    Ok((host, String::from(path)))
+}
@@ -0,0 +1,380 @@
+//!
+//! A handler to push log records to browsers through a WebSocket server.
+//!
+
+use handlers::{Handle, Filter};
+use log::LogLevelFilter;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use rustc_serialize::json::{self, Json};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ExtendedLogRecord;
+
+/// GUID used to compute the handshake answer (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Maximum number of frames waiting to be sent to a client before it is dropped.
+const CLIENT_BACKLOG: usize = 1024;
+
+/// Maximum size of a message sent by a client.
+const MAX_MESSAGE_SIZE: u64 = 65536;
+
+/// Delay given to a client to send its HTTP request, in seconds.
+const REQUEST_TIMEOUT: u64 = 5;
+
+/// Page served on plain HTTP requests, it renders the records pushed by the server.
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>log-tools</title>
+<style>
+body { font-family: monospace; margin: 0; }
+form { position: sticky; top: 0; padding: 8px; background: #eee; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 2px 8px; vertical-align: top; white-space: pre-wrap; }
+.ERROR { color: #c00; } .WARN { color: #b60; } .DEBUG, .TRACE { color: #888; }
+</style>
+</head>
+<body>
+<form id="subscription">
+  <select id="level">
+    <option>ERROR</option><option>WARN</option><option selected>INFO</option><option>DEBUG</option><option>TRACE</option>
+  </select>
+  <input id="target" placeholder="target prefix">
+  <button>Subscribe</button>
+</form>
+<table id="records"></table>
+<script>
+var socket = new WebSocket("ws://" + location.host + "/");
+var form = document.getElementById("subscription");
+function subscribe() {
+  socket.send(JSON.stringify({
+    level: document.getElementById("level").value,
+    target: document.getElementById("target").value
+  }));
+}
+form.onsubmit = function (event) { event.preventDefault(); subscribe(); };
+socket.onopen = subscribe;
+socket.onmessage = function (event) {
+  var record = JSON.parse(event.data);
+  var row = document.getElementById("records").insertRow(-1);
+  row.className = record.level;
+  [record.date, record.level, record.target, record.msg].forEach(function (value) {
+    row.insertCell(-1).textContent = value;
+  });
+  window.scrollTo(0, document.body.scrollHeight);
+};
+</script>
+</body>
+</html>
+"#;
+
+/// A client connected to the WebSocket server.
+struct WebSocketClient {
+    /// The client socket, used to disconnect it.
+    stream: TcpStream,
+    /// Queue of the frames to send to the client.
+    frames: SyncSender<Vec<u8>>,
+    /// The maximum log level requested by the client.
+    level: LogLevelFilter,
+    /// Only the records which target starts with this prefix are sent.
+    target: String,
+}
+
+impl WebSocketClient {
+    /// Determines if the record was requested by the client.
+    fn accept(&self, record: &ExtendedLogRecord) -> bool {
+        self.level >= record.level() && record.target.starts_with(self.target.as_str())
+    }
+}
+
+/// Connected clients by identifier.
+#[derive(Default)]
+struct Registry {
+    /// Identifier of the next client.
+    next_id: usize,
+    /// The clients which completed the WebSocket handshake.
+    clients: HashMap<usize, WebSocketClient>,
+}
+
+/// Handler which pushes the records encoded in JSON to WebSocket clients.
+///
+/// Plain HTTP requests are answered with a page which subscribes to the server and renders the
+/// records. A client subscribes by sending a JSON text message like
+/// `{"level": "WARN", "target": "db"}`; both fields are optional. Until then, it receives every
+/// record accepted by the handler.
+///
+/// Each client has its own writer thread and queue, a client which lets its queue fill up is
+/// disconnected rather than blocking the logger.
+///
+/// Upgrades sent by a browser from a page of another origin than the server are rejected, so
+/// that other sites can't read the records.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut hdlr = WebSocketHandler::new("127.0.0.1:9998", Some(LogLevelFilter::Debug)).unwrap();
+///
+/// hdlr.handle(&rec);
+/// ```
+///
+/// Then browse `http://127.0.0.1:9998/`.
+pub struct WebSocketHandler {
+    /// Vector of filter callback.
+    pub filters: Vec<fn(&ExtendedLogRecord) -> bool>,
+    /// The current maximum log level of the handler.
+    pub level: LogLevelFilter,
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// The connected clients.
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl WebSocketHandler {
+    /// Create a new handler instance and start listening on `address`.
+    ///
+    /// Fails if the server cannot listen on `address`, e.g. when the port is already in use.
+    pub fn new(address: &str, level: Option<LogLevelFilter>) -> io::Result<WebSocketHandler> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let shared = registry.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let registry = shared.clone();
+                thread::spawn(move || serve(stream, registry));
+            }
+        });
+        Ok(WebSocketHandler {
+            filters: vec![],
+            level: level.unwrap_or(LogLevelFilter::Off),
+            address,
+            registry,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.registry.lock().unwrap().clients.len()
+    }
+}
+
+/// Answer an HTTP request, either with the page or by upgrading the connection.
+fn serve(stream: TcpStream, registry: Arc<Mutex<Registry>>) {
+    if stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT))).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(stream);
+    let mut key = None;
+    let mut host = None;
+    let mut origin = None;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut header = line.splitn(2, ':');
+        let name = header.next().unwrap_or("").trim().to_lowercase();
+        let value = header.next().map(|value| String::from(value.trim()));
+        match name.as_str() {
+            "sec-websocket-key" => key = value,
+            "host" => host = value,
+            "origin" => origin = value,
+            _ => {}
+        }
+    }
+    let _ = match key {
+        Some(_) if !same_origin(origin.as_ref(), host.as_ref()) => write!(
+            reader.get_mut(),
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        ),
+        Some(key) => upgrade(reader, &key, registry),
+        None => write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            INDEX_HTML.len(), INDEX_HTML
+        ),
+    };
+}
+
+/// Determines if an upgrade comes from the page of the server. Clients which are not browsers
+/// don't send an origin.
+fn same_origin(origin: Option<&String>, host: Option<&String>) -> bool {
+    let origin = match origin {
+        Some(origin) => origin,
+        None => return true,
+    };
+    let origin = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://"));
+    match (origin, host) {
+        (Some(origin), Some(host)) => origin.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+/// Complete the WebSocket handshake, then read the client messages until it disconnects.
+fn upgrade(mut reader: BufReader<TcpStream>, key: &str, registry: Arc<Mutex<Registry>>) -> io::Result<()> {
+    // The client may then stay silent for as long as it wants.
+    reader.get_ref().set_read_timeout(None)?;
+    let accept = Sha1::from(format!("{}{}", key, WEBSOCKET_GUID)).digest().bytes().to_base64(STANDARD);
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    let writer = reader.get_ref().try_clone()?;
+    let (frames, queue) = mpsc::sync_channel(CLIENT_BACKLOG);
+    let id = {
+        let mut registry = registry.lock().unwrap();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.clients.insert(id, WebSocketClient {
+            stream: writer.try_clone()?,
+            frames: frames.clone(),
+            level: LogLevelFilter::Trace,
+            target: String::new(),
+        });
+        id
+    };
+    thread::spawn(move || send(writer, queue));
+    let result = receive(&mut reader, id, &frames, &registry);
+    registry.lock().unwrap().clients.remove(&id);
+    let _ = frames.try_send(frame(0x8, &[]));
+    result
+}
+
+/// Write the queued frames to the client until the queue is dropped.
+fn send(mut stream: TcpStream, queue: Receiver<Vec<u8>>) {
+    for data in queue {
+        if stream.write_all(&data).is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Read the messages of a client and update its subscription.
+fn receive(reader: &mut BufReader<TcpStream>, id: usize, frames: &SyncSender<Vec<u8>>, registry: &Arc<Mutex<Registry>>) -> io::Result<()> {
+    loop {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let opcode = head[0] & 0x0F;
+        let mut length = (head[1] & 0x7F) as u64;
+        if length == 126 {
+            let mut extended = [0; 2];
+            reader.read_exact(&mut extended)?;
+            length = extended.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64);
+        } else if length == 127 {
+            let mut extended = [0; 8];
+            reader.read_exact(&mut extended)?;
+            length = extended.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64);
+        }
+        if length > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+        }
+        let mut mask = [0; 4];
+        if head[1] & 0x80 != 0 {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+        for (idx, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[idx % 4];
+        }
+        match opcode {
+            0x1 => subscribe(&payload, id, registry),
+            0x8 => return Ok(()),
+            0x9 => {
+                let _ = frames.try_send(frame(0xA, &payload));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Apply a subscription message to a client.
+fn subscribe(payload: &[u8], id: usize, registry: &Arc<Mutex<Registry>>) {
+    let message = match Json::from_str(&String::from_utf8_lossy(payload)) {
+        Ok(message) => message,
+        Err(_) => return,
+    };
+    let mut registry = registry.lock().unwrap();
+    if let Some(client) = registry.clients.get_mut(&id) {
+        if let Some(level) = message.find("level").and_then(|level| level.as_string()) {
+            if let Ok(level) = LogLevelFilter::from_str(level) {
+                client.level = level;
+            }
+        }
+        if let Some(target) = message.find("target").and_then(|target| target.as_string()) {
+            client.target = String::from(target);
+        }
+    }
+}
+
+/// Build an unmasked frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x80 | opcode];
+    let length = payload.len();
+    if length < 126 {
+        data.push(length as u8);
+    } else if length <= 0xFFFF {
+        data.push(126);
+        data.extend_from_slice(&[(length >> 8) as u8, length as u8]);
+    } else {
+        data.push(127);
+        data.extend((0..8).rev().map(|shift| (length as u64 >> (shift * 8)) as u8));
+    }
+    data.extend_from_slice(payload);
+    data
+}
+
+impl Filter for WebSocketHandler {
+    /// Apply all stored filters to emit or not the log record
+    fn filter(&self, record: &ExtendedLogRecord) -> bool {
+        self.filters.iter().all(|filter| filter(record))
+    }
+}
+
+impl Handle for WebSocketHandler {
+    /// Check if the log record may be emitted. `self.level` and filters will be checked.
+    fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        if self.level >= record.level() && self.filter(record) {
+            self.emit(record)
+        } else {
+            Ok(())
+        }
+    }
+    /// Queue the record encoded in JSON for the subscribed clients, dropping the slow ones.
+    fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+        let mut registry = self.registry.lock().unwrap();
+        if registry.clients.is_empty() {
+            return Ok(());
+        }
+        let data = frame(0x1, json::encode(record).unwrap().as_bytes());
+        registry.clients.retain(|_, client| {
+            if !client.accept(record) || client.frames.try_send(data.clone()).is_ok() {
+                return true;
+            }
+            let _ = client.stream.shutdown(Shutdown::Both);
+            false
+        });
+        Ok(())
+    }
+}
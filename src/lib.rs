@@ -10,6 +10,8 @@
 //! handlers.
 //! * [rustc-serialize](https://doc.rust-lang.org/rustc-serialize) - adds the ability to serialize and deserialize a `ExtendedLogRecord`
 //!   using the `rustc-serialize` crate.
+//! * [sha1](https://docs.rs/sha1) - minimal SHA-1 implementation used by the WebSocket handshake.
+//! * [regex](https://docs.rs/regex) - regular expressions used to extract metrics from the log records.
 //!
 //! By default, `log-tools` can be depended on with:
 //!
@@ -55,21 +57,50 @@
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
+extern crate regex;
 extern crate rustc_serialize;
+extern crate sha1;
 extern crate time;
 
+#[macro_use]
 pub mod handlers;
 pub mod formatter;
 
 #[cfg(test)]
 mod tests;
 
+use handlers::alert::{Alert, AlertHandler};
+use handlers::capture::CaptureHandler;
+use handlers::channel::ChannelHandler;
+use handlers::custom::CustomHandler;
+use handlers::dedup::{DedupHandler, DedupKey};
+use handlers::digest::DigestHandler;
+use handlers::extract::{ExtractHandler, ExtractedMetrics, MetricRule};
+use handlers::failover::FailoverHandler;
+use handlers::memory::MemoryHandler;
+use handlers::metrics::{Metrics, MetricsHandler};
+use handlers::pipe::PipeHandler;
+use handlers::queue::{Overflow, QueueHandler, QueueStats};
+use handlers::ratelimit::{RateLimitHandler, RateLimitKey};
+use handlers::ring::{RingBuffer, RingHandler};
+use handlers::routing::{Route, RoutingHandler};
+use handlers::sampling::SamplingHandler;
+use handlers::scope::ScopeHandler;
+use handlers::statsd::StatsdHandler;
 use handlers::streams::file::FileHandler;
 use handlers::streams::net::TCPHandler;
+use handlers::streams::stderr::StderrHandler;
 use handlers::streams::stdout::StdoutHandler;
-use handlers::{Handler, HANDLERS, NullHandler};
+use handlers::streams::writer::WriterHandler;
+use handlers::tail::TailHandler;
+use handlers::webhook::WebhookHandler;
+use handlers::websocket::WebSocketHandler;
+use handlers::{Handle, Handler, HANDLERS, NullHandler};
 use log::{LogRecord, LogLevel, LogMetadata, LogLevelFilter, SetLoggerError};
+use std::io::{self, Write};
 use std::str::FromStr;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
 
 /// A custom logger
 pub struct ExtendedLogger {
@@ -95,7 +126,8 @@ impl log::Log for ExtendedLogger {
         if self.enabled(record.metadata()) {
             let ext_record = ExtendedLogRecord::from(record);
             for hdlr in HANDLERS.lock().unwrap().iter_mut() {
-                hdlr.handle(&ext_record)
+                // A failing handler must not prevent the others from receiving the record.
+                let _ = hdlr.handle(&ext_record);
             }
         }
     }
@@ -128,13 +160,145 @@ impl ExtendedLogger {
     pub fn add_file_handler(filename: &'static str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
         ExtendedLogger::add_handler(Handler::from(FileHandler::new(filename, level, formatter)))
     }
-    pub fn add_tcp_handler(address: &'static str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
+    pub fn add_tcp_handler(address: &str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
         ExtendedLogger::add_handler(Handler::from(TCPHandler::new(address, level, formatter)))
     }
+    /// Append a handler posting alerts to the webhook at `url`, which must be an `http://` URL.
+    pub fn add_webhook_handler(url: &str, level: Option<LogLevelFilter>, template: Option<&str>) -> io::Result<()> {
+        ExtendedLogger::add_handler(Handler::from(WebhookHandler::new(url, level, template)?));
+        Ok(())
+    }
+    pub fn add_pipe_handler(program: &str, args: &[&str], level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
+        ExtendedLogger::add_handler(Handler::from(PipeHandler::new(program, args, level, formatter)))
+    }
+    /// Append a handler streaming the records to the clients connected on `address`.
+    pub fn add_tail_handler(address: &str, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) -> io::Result<()> {
+        ExtendedLogger::add_handler(Handler::from(TailHandler::new(address, level, formatter)?));
+        Ok(())
+    }
+    /// Append a handler pushing the records to the WebSocket clients connected on `address`.
+    pub fn add_websocket_handler(address: &str, level: Option<LogLevelFilter>) -> io::Result<()> {
+        ExtendedLogger::add_handler(Handler::from(WebSocketHandler::new(address, level)?));
+        Ok(())
+    }
+    pub fn add_stderr_handler(level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
+        ExtendedLogger::add_handler(Handler::from(StderrHandler::new(level, formatter)))
+    }
+    /// Append a stderr handler which colors the records when stderr is a terminal.
+    ///
+    /// See `StderrHandler::console`.
+    pub fn add_console_handler(level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>, color_formatter: Option<fn(&ExtendedLogRecord) -> String>) {
+        ExtendedLogger::add_handler(Handler::from(StderrHandler::console(level, formatter, color_formatter)))
+    }
+    pub fn add_memory_handler(target: Handler, capacity: usize, flush_level: LogLevel, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(MemoryHandler::new(target, capacity, flush_level, level)))
+    }
+    /// Append a ring handler and return the buffer to query its records.
+    pub fn add_ring_handler(max_records: Option<usize>, max_bytes: Option<usize>, level: Option<LogLevelFilter>) -> RingBuffer {
+        let hdlr = RingHandler::new(max_records, max_bytes, level);
+        let buffer = hdlr.buffer();
+        ExtendedLogger::add_handler(Handler::from(hdlr));
+        buffer
+    }
+    /// Append a queue handler which feeds `handlers` from a background thread and return its
+    /// counters.
+    pub fn add_queue_handler(handlers: Vec<Handler>, capacity: usize, overflow: Overflow, level: Option<LogLevelFilter>) -> QueueStats {
+        let hdlr = QueueHandler::new(handlers, capacity, overflow, level);
+        let stats = hdlr.stats();
+        ExtendedLogger::add_handler(Handler::from(hdlr));
+        stats
+    }
+    pub fn add_channel_handler(sender: Sender<OwnedLogRecord>, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(ChannelHandler::new(sender, level)))
+    }
+    pub fn add_callback_handler<F>(callback: F, level: Option<LogLevelFilter>)
+        where F: FnMut(OwnedLogRecord) + Send + 'static
+    {
+        ExtendedLogger::add_handler(Handler::from(ChannelHandler::with_callback(callback, level)))
+    }
+    /// Append a user defined handler, given the records up to `level`.
+    pub fn add_custom_handler<H: Handle + Send + 'static>(hdlr: H, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(CustomHandler::new(Box::new(hdlr), level)))
+    }
+    pub fn add_writer_handler(writer: Box<dyn Write + Send>, level: Option<LogLevelFilter>, formatter: Option<fn(&ExtendedLogRecord) -> String>) {
+        ExtendedLogger::add_handler(Handler::from(WriterHandler::new(writer, level, formatter)))
+    }
+    pub fn add_routing_handler(routes: Vec<Route>, fallback: Vec<Handler>, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(RoutingHandler::new(routes, fallback, level)))
+    }
+    pub fn add_failover_handler(primary: Handler, secondary: Handler, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(FailoverHandler::new(primary, secondary, level)))
+    }
+    pub fn add_capture_handler(level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(CaptureHandler::new(level)))
+    }
+    /// Append a metrics handler, serving the counters on `address` if given, and return the
+    /// counters.
+    pub fn add_metrics_handler(level: Option<LogLevelFilter>, per_module: bool, address: Option<&str>) -> io::Result<Metrics> {
+        let mut hdlr = MetricsHandler::new(level);
+        hdlr.per_module = per_module;
+        let metrics = hdlr.metrics();
+        if let Some(address) = address {
+            metrics.serve(address)?;
+        }
+        ExtendedLogger::add_handler(Handler::from(hdlr));
+        Ok(metrics)
+    }
+    pub fn add_dedup_handler(target: Handler, key: DedupKey, window: Option<Duration>, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(DedupHandler::new(target, key, window, level)))
+    }
+    pub fn add_ratelimit_handler(target: Handler, key: RateLimitKey, burst: u32, refill: f64, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(RateLimitHandler::new(target, key, burst, refill, level)))
+    }
+    /// Append a sampling handler keeping the given fraction of the records of each level.
+    pub fn add_sampling_handler(target: Handler, rates: &[(LogLevel, f64)], key: Option<fn(&ExtendedLogRecord) -> String>, level: Option<LogLevelFilter>) {
+        let mut hdlr = SamplingHandler::new(target, level);
+        for &(level, rate) in rates {
+            hdlr.set_rate(level, rate);
+        }
+        hdlr.key = key;
+        ExtendedLogger::add_handler(Handler::from(hdlr))
+    }
+    pub fn add_digest_handler(target: Handler, window: Duration, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(DigestHandler::new(target, window, level)))
+    }
+    pub fn add_alert_handler(target: Handler, threshold: usize, window: Duration, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(AlertHandler::new(target, threshold, window, level)))
+    }
+    pub fn add_alert_callback_handler<F>(callback: F, threshold: usize, window: Duration, level: Option<LogLevelFilter>)
+        where F: FnMut(&Alert) + Send + 'static
+    {
+        ExtendedLogger::add_handler(Handler::from(AlertHandler::with_callback(callback, threshold, window, level)))
+    }
+    pub fn add_scope_handler(target: Handler, base_level: LogLevelFilter, level: Option<LogLevelFilter>) {
+        ExtendedLogger::add_handler(Handler::from(ScopeHandler::new(target, base_level, level)))
+    }
+    /// Append a handler counting the records with the StatsD agent at `address`.
+    pub fn add_statsd_handler(address: &str, metric: &str, tags: &[&str], level: Option<LogLevelFilter>) -> io::Result<()> {
+        let mut hdlr = StatsdHandler::new(address, metric, level)?;
+        hdlr.tags = tags.iter().map(|tag| String::from(*tag)).collect();
+        ExtendedLogger::add_handler(Handler::from(hdlr));
+        Ok(())
+    }
+    /// Append a handler extracting metrics with `rules` and return the metrics.
+    pub fn add_extract_handler(rules: Vec<MetricRule>, level: Option<LogLevelFilter>) -> ExtractedMetrics {
+        let hdlr = ExtractHandler::new(rules, level);
+        let metrics = hdlr.metrics();
+        ExtendedLogger::add_handler(Handler::from(hdlr));
+        metrics
+    }
     /// Append a new handler.
     fn add_handler(hdlr: Handler) {
         HANDLERS.lock().unwrap().push(hdlr);
     }
+    /// Remove and close all the handlers.
+    ///
+    /// Handlers are never dropped otherwise, call it before exiting to let them release their
+    /// resources (child processes, buffers, queues...).
+    pub fn shutdown() {
+        let handlers: Vec<Handler> = HANDLERS.lock().unwrap().drain(..).collect();
+        drop(handlers);
+    }
 }
 
 /// Extended log record.
@@ -160,6 +324,8 @@ pub struct ExtendedLogRecord<'a> {
     pub target: String,
     /// The message creation timestamp.
     pub timestamp: i64,
+    /// The fraction of the records of this level kept by a `SamplingHandler`, if any.
+    pub sample_rate: Option<f64>,
 }
 
 /// Construct a `ExtendedLogRecord` via a conversion from a `LogRecord`.
@@ -192,6 +358,76 @@ impl<'a> ExtendedLogRecord<'a> {
             msg: msg,
             target: target,
             timestamp: now.to_timespec().sec,
+            sample_rate: None,
+        }
+    }
+
+    /// Recover log record level by its name to allow level comparison.
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_str(self.level.as_str()).unwrap()
+    }
+}
+
+/// Owned log record.
+///
+/// Unlike `ExtendedLogRecord`, it doesn't borrow anything and may be stored or sent to another
+/// thread by the handlers.
+#[derive(Clone, Debug, RustcEncodable)]
+pub struct OwnedLogRecord {
+    /// The message creation formatted according to RFC 3339. RFC 3339 is compatible with ISO 8601.
+    pub date: String,
+    /// The source file containing the message.
+    pub file: String,
+    /// The verbosity level name of the message.
+    pub level: String,
+    /// The verbosity level value of the message.
+    pub levelno: u32,
+    /// The line containing the message.
+    pub line: u32,
+    /// The module path of the message.
+    pub module: String,
+    /// The message body.
+    pub msg: String,
+    /// The message factory.
+    pub target: String,
+    /// The message creation timestamp.
+    pub timestamp: i64,
+    /// The fraction of the records of this level kept by a `SamplingHandler`, if any.
+    pub sample_rate: Option<f64>,
+}
+
+/// Construct a `OwnedLogRecord` via a copy of a `ExtendedLogRecord`.
+impl<'a, 'b> From<&'b ExtendedLogRecord<'a>> for OwnedLogRecord {
+    fn from(record: &'b ExtendedLogRecord<'a>) -> OwnedLogRecord {
+        OwnedLogRecord {
+            date: record.date.clone(),
+            file: String::from(record.file),
+            level: record.level.clone(),
+            levelno: record.levelno,
+            line: record.line,
+            module: String::from(record.module),
+            msg: record.msg.clone(),
+            target: record.target.clone(),
+            timestamp: record.timestamp,
+            sample_rate: record.sample_rate,
+        }
+    }
+}
+
+impl OwnedLogRecord {
+    /// Borrow the record as a `ExtendedLogRecord` to pass it to handlers and formatters.
+    pub fn as_record(&self) -> ExtendedLogRecord<'_> {
+        ExtendedLogRecord {
+            date: self.date.clone(),
+            file: self.file.as_str(),
+            level: self.level.clone(),
+            levelno: self.levelno,
+            line: self.line,
+            module: self.module.as_str(),
+            msg: self.msg.clone(),
+            target: self.target.clone(),
+            timestamp: self.timestamp,
+            sample_rate: self.sample_rate,
         }
     }
 
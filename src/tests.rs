@@ -1,12 +1,15 @@
 use log::{LogLevelFilter, LogLevel};
 use ExtendedLogger;
 use handlers::Handler;
-use formatter::{default, json, pretty_json};
+use formatter::{default, json, logfmt, pretty_json};
 use handlers::streams::stdout::StdoutHandler;
 use handlers::streams::file::FileHandler;
 use handlers::streams::net::TCPHandler;
+use handlers::ring::{RingBuffer, RingHandler};
 use ExtendedLogRecord;
 use handlers::Handle;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
 
 fn create_record(msg: &'static str) -> ExtendedLogRecord {
     ExtendedLogRecord::new(
@@ -19,6 +22,41 @@ fn create_record(msg: &'static str) -> ExtendedLogRecord {
     )
 }
 
+fn create_leveled_record(level: LogLevel, msg: &'static str) -> ExtendedLogRecord {
+    ExtendedLogRecord::new(
+        file!(),
+        level,
+        line!(),
+        module_path!(),
+        msg.to_string(),
+        "TestFactory".to_string()
+    )
+}
+
+/// Ring keeping every record, with the buffer to read them back.
+fn recorder() -> (RingHandler, RingBuffer) {
+    let ring = RingHandler::new(None, None, Some(LogLevelFilter::Trace));
+    let buffer = ring.buffer();
+    (ring, buffer)
+}
+
+/// Messages of the records kept in a ring buffer.
+fn messages(buffer: &RingBuffer) -> Vec<String> {
+    buffer.snapshot().into_iter().map(|record| record.msg).collect()
+}
+
+/// Writer appending to a vector shared with the test.
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 #[test]
 fn test_stdout_logger() {
     ExtendedLogger::init(LogLevelFilter::Info).unwrap();
@@ -37,7 +75,7 @@ fn test_stdout_json() {
         Some(LogLevelFilter::Info),
         Some(json)
     );
-    hdlr.handle(&rec);
+    hdlr.handle(&rec).unwrap();
 }
 
 #[test]
@@ -49,14 +87,14 @@ fn test_file_json() {
         Some(LogLevelFilter::Info),
         Some(json),
     );
-    hdlr.handle(&rec);
+    hdlr.handle(&rec).unwrap();
 }
 
 #[test]
 fn test_stdout_pretty_json() {
     let rec = create_record("Test - StdoutHandler - pretty_json");
     let mut hdlr = StdoutHandler::new(Some(LogLevelFilter::Info), Some(pretty_json));
-    hdlr.handle(&rec);
+    hdlr.handle(&rec).unwrap();
 }
 
 fn custom_formatter(record: &ExtendedLogRecord) -> String {
@@ -67,7 +105,7 @@ fn custom_formatter(record: &ExtendedLogRecord) -> String {
 fn test_stdout_custom() {
     let rec = create_record("Test - StdoutHandler - custom_formatter");
     let mut hdlr = StdoutHandler::new(Some(LogLevelFilter::Info), Some(custom_formatter));
-    hdlr.handle(&rec);
+    hdlr.handle(&rec).unwrap();
 }
 
 #[test]
@@ -86,4 +124,1125 @@ fn format_json() {
 fn format_pretty_json() {
     let rec = create_record("test");
     println!("{}", pretty_json(&rec));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_webhook_rate_limit() {
+    use handlers::webhook::WebhookHandler;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hooks/test", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut bodies = vec![];
+        for (idx, stream) in listener.incoming().take(3).enumerate() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("Content-Length: ") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let status = if idx == 1 { "500 Internal Server Error" } else { "200 OK" };
+            let reply = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            bodies.push(String::from_utf8(body).unwrap());
+        }
+        bodies
+    });
+
+    assert!(WebhookHandler::new("https://hooks.slack.com/services/xxx", Some(LogLevelFilter::Info), None).is_err());
+    let mut hdlr = WebhookHandler::new(&url, Some(LogLevelFilter::Info), Some("{level} {msg}")).unwrap();
+    hdlr.handle(&create_record("first")).unwrap();
+    hdlr.handle(&create_record("second")).unwrap();
+    hdlr.handle(&create_record("third")).unwrap();
+    hdlr.interval = Duration::from_secs(0);
+    assert!(hdlr.handle(&create_record("fourth")).is_err());
+    // The failed post is not retried before the interval is over.
+    hdlr.interval = Duration::from_secs(60);
+    hdlr.handle(&create_record("fifth")).unwrap();
+    hdlr.interval = Duration::from_secs(0);
+    hdlr.flush().unwrap();
+
+    let bodies = server.join().unwrap();
+    assert_eq!(bodies[0], r#"{"text":"INFO first"}"#);
+    assert_eq!(bodies[1], r#"{"text":"INFO fourth\n_2 similar alert(s) suppressed_"}"#);
+    assert_eq!(bodies[2], r#"{"text":"_4 similar alert(s) from TestFactory suppressed_"}"#);
+}
+
+#[test]
+fn test_pipe_handler() {
+    use handlers::pipe::PipeHandler;
+    use std::fs::File;
+    use std::io::Read;
+
+    let path = "/tmp/log-tools-pipe.txt";
+    {
+        let mut hdlr = PipeHandler::new("sh", &["-c", "cat > /tmp/log-tools-pipe.txt"], Some(LogLevelFilter::Info), Some(custom_formatter));
+        hdlr.handle(&create_record("Test - PipeHandler - first")).unwrap();
+        hdlr.handle(&create_record("Test - PipeHandler - second")).unwrap();
+    }
+    let mut content = String::new();
+    File::open(path).unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content.lines().count(), 2);
+    assert!(content.ends_with("Test - PipeHandler - second\n"));
+}
+
+#[test]
+fn test_pipe_handler_backoff() {
+    use handlers::pipe::PipeHandler;
+
+    let mut hdlr = PipeHandler::new("/nonexistent/command", &[], Some(LogLevelFilter::Info), None);
+    hdlr.capacity = 2;
+    hdlr.handle(&create_record("first")).unwrap();
+    hdlr.handle(&create_record("second")).unwrap();
+    hdlr.handle(&create_record("third")).unwrap();
+    assert_eq!(hdlr.pending(), 2);
+    assert!(hdlr.flush().is_err());
+
+    let mut hdlr = PipeHandler::new("/nonexistent/command", &[], Some(LogLevelFilter::Info), None);
+    hdlr.capacity = 0;
+    assert!(hdlr.handle(&create_record("dropped")).is_err());
+    assert_eq!(hdlr.pending(), 0);
+}
+
+#[test]
+fn test_pipe_handler_stop_timeout() {
+    use handlers::pipe::PipeHandler;
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    {
+        let mut hdlr = PipeHandler::new("sleep", &["30"], Some(LogLevelFilter::Info), None);
+        hdlr.stop_timeout = Duration::from_millis(100);
+        // More than the pipe can hold: the command never reads, the logger must not block.
+        fn padded(record: &ExtendedLogRecord) -> String {
+            format!("{:1024}\n", record.msg)
+        }
+        hdlr.formatter = padded;
+        for _ in 0..200 {
+            hdlr.handle(&create_record("Test - PipeHandler - ignored")).unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_tail_handler() {
+    use handlers::tail::TailHandler;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut hdlr = TailHandler::new("127.0.0.1:0", Some(LogLevelFilter::Debug), Some(custom_formatter)).unwrap();
+    assert!(TailHandler::new(&hdlr.local_addr().to_string(), None, None).is_err());
+    let mut stream = TcpStream::connect(hdlr.local_addr()).unwrap();
+    stream.write_all(b"WARN Test\n").unwrap();
+    // A client without handshake receives every record.
+    let bare = TcpStream::connect(hdlr.local_addr()).unwrap();
+    while hdlr.clients() < 2 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "Test - TailHandler - info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - TailHandler - error")).unwrap();
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+    assert!(line.ends_with("ERROR - Test - TailHandler - error\n"));
+    let mut line = String::new();
+    BufReader::new(bare).read_line(&mut line).unwrap();
+    assert!(line.ends_with("INFO - Test - TailHandler - info\n"));
+}
+
+#[test]
+fn test_websocket_handler() {
+    use handlers::websocket::WebSocketHandler;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+
+    fn read_frame(reader: &mut BufReader<TcpStream>) -> (u8, String) {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head).unwrap();
+        let mut length = (head[1] & 0x7F) as usize;
+        if length == 126 {
+            let mut extended = [0; 2];
+            reader.read_exact(&mut extended).unwrap();
+            length = (extended[0] as usize) << 8 | extended[1] as usize;
+        }
+        let mut payload = vec![0; length];
+        reader.read_exact(&mut payload).unwrap();
+        (head[0] & 0x0F, String::from_utf8(payload).unwrap())
+    }
+
+    fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) {
+        let mask = [1, 2, 3, 4];
+        let mut data = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&mask);
+        data.extend(payload.iter().enumerate().map(|(idx, byte)| byte ^ mask[idx % 4]));
+        stream.write_all(&data).unwrap();
+    }
+
+    let mut hdlr = WebSocketHandler::new("127.0.0.1:0", Some(LogLevelFilter::Debug)).unwrap();
+    assert!(WebSocketHandler::new(&hdlr.local_addr().to_string(), None).is_err());
+
+    let mut page = String::new();
+    let mut stream = TcpStream::connect(hdlr.local_addr()).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    stream.read_to_string(&mut page).unwrap();
+    assert!(page.starts_with("HTTP/1.1 200 OK"));
+    assert!(page.contains("new WebSocket"));
+
+    let mut reply = String::new();
+    let mut stream = TcpStream::connect(hdlr.local_addr()).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nOrigin: http://evil.example\r\nUpgrade: websocket\r\n\
+                       Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 403"));
+
+    let mut stream = TcpStream::connect(hdlr.local_addr()).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nOrigin: http://localhost\r\nUpgrade: websocket\r\n\
+                       Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut headers = String::new();
+    while !headers.ends_with("\r\n\r\n") {
+        reader.read_line(&mut headers).unwrap();
+    }
+    assert!(headers.starts_with("HTTP/1.1 101"));
+    assert!(headers.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    while hdlr.clients() == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    write_frame(&mut stream, 0x1, br#"{"level": "WARN", "target": "Test"}"#);
+    write_frame(&mut stream, 0x9, b"ping");
+    assert_eq!(read_frame(&mut reader), (0xA, String::from("ping")));
+
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "Test - WebSocketHandler - info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - WebSocketHandler - error")).unwrap();
+    let (opcode, payload) = read_frame(&mut reader);
+    assert_eq!(opcode, 0x1);
+    assert!(payload.contains(r#""msg":"Test - WebSocketHandler - error""#));
+}
+
+#[test]
+fn format_text() {
+    use formatter::text;
+
+    let rec = create_record("test");
+    assert_eq!(text(&rec), format!("{} INFO  TestFactory: test\n", rec.date));
+}
+
+#[test]
+fn format_colored() {
+    use formatter::colored;
+
+    let rec = create_leveled_record(LogLevel::Error, "test");
+    assert_eq!(colored(&rec), format!("{} \x1b[1;31mERROR\x1b[0m TestFactory: test\n", rec.date));
+}
+
+#[test]
+fn test_stderr_console() {
+    use handlers::streams::console::colors_for;
+    use handlers::streams::stderr::StderrHandler;
+    use handlers::streams::writer::WriterHandler;
+    use std::ffi::OsStr;
+
+    // Handle an info and a warning through a WARN console, return what was written.
+    let render = |colors: bool| {
+        let output = Arc::new(Mutex::new(vec![]));
+        let mut hdlr = WriterHandler::console(Box::new(SharedBuffer(output.clone())), colors, Some(LogLevelFilter::Warn), None, None);
+        hdlr.handle(&create_leveled_record(LogLevel::Info, "Test - WriterHandler - info")).unwrap();
+        hdlr.handle(&create_leveled_record(LogLevel::Warn, "Test - WriterHandler - warn")).unwrap();
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        output
+    };
+
+    let output = render(true);
+    assert_eq!(output.lines().count(), 1);
+    assert!(output.ends_with(" \x1b[33mWARN \x1b[0m TestFactory: Test - WriterHandler - warn\n"));
+    assert!(render(false).ends_with(" WARN  TestFactory: Test - WriterHandler - warn\n"));
+
+    let mut stderr = StderrHandler::console(Some(LogLevelFilter::Info), None, None);
+    stderr.handle(&create_record("Test - StderrHandler - console")).unwrap();
+    stderr.handle(&create_leveled_record(LogLevel::Debug, "Test - StderrHandler - ignored")).unwrap();
+
+    let (set, empty, zero) = (Some(OsStr::new("1")), Some(OsStr::new("")), Some(OsStr::new("0")));
+    assert!(colors_for(None, None, true));
+    assert!(!colors_for(None, None, false));
+    assert!(colors_for(empty, None, true));
+    assert!(colors_for(None, set, false));
+    assert!(!colors_for(None, zero, false));
+    assert!(!colors_for(None, empty, false));
+    assert!(!colors_for(set, None, true));
+    assert!(!colors_for(set, set, true));
+}
+
+#[test]
+fn test_memory_handler() {
+    use handlers::memory::MemoryHandler;
+    use std::fs::{self, File};
+    use std::io::Read;
+
+    let path = "/tmp/log-tools-memory.txt";
+    let _ = fs::remove_file(path);
+    let mut hdlr = MemoryHandler::new(
+        Handler::from(FileHandler::new(path, Some(LogLevelFilter::Debug), Some(custom_formatter))),
+        2,
+        LogLevel::Error,
+        Some(LogLevelFilter::Debug),
+    );
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "first")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "second")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Trace, "ignored")).unwrap();
+    assert_eq!(hdlr.len(), 2);
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "failure")).unwrap();
+    assert_eq!(hdlr.len(), 0);
+
+    let mut content = String::new();
+    File::open(path).unwrap().read_to_string(&mut content).unwrap();
+    let msgs: Vec<&str> = content.lines().map(|line| line.rsplit(" - ").next().unwrap()).collect();
+    assert_eq!(msgs, vec!["second", "failure"]);
+}
+
+#[test]
+fn test_memory_handler_no_capacity() {
+    use handlers::memory::MemoryHandler;
+
+    let ring = RingHandler::new(Some(10), None, Some(LogLevelFilter::Trace));
+    let buffer = ring.buffer();
+    let mut hdlr = MemoryHandler::new(Handler::from(ring), 0, LogLevel::Error, Some(LogLevelFilter::Debug));
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "first")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "failure")).unwrap();
+    assert!(hdlr.is_empty());
+
+    let msgs = messages(&buffer);
+    assert_eq!(msgs, vec!["failure"]);
+}
+
+#[test]
+fn test_ring_handler() {
+    let mut hdlr = RingHandler::new(Some(3), None, Some(LogLevelFilter::Debug));
+    let buffer = hdlr.buffer();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "first")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "second")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "third")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Warn, "fourth")).unwrap();
+
+    let msgs = messages(&buffer);
+    assert_eq!(msgs, vec!["second", "third", "fourth"]);
+    let msgs: Vec<String> = buffer.query(LogLevelFilter::Warn, "Test").into_iter().map(|record| record.msg).collect();
+    assert_eq!(msgs, vec!["second", "fourth"]);
+    assert!(buffer.query(LogLevelFilter::Trace, "db").is_empty());
+    assert!(buffer.to_json().starts_with(r#"[{"date":"#));
+    assert_eq!(buffer.format(custom_formatter).lines().count(), 3);
+    buffer.clear();
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_ring_handler_bytes() {
+    let mut hdlr = RingHandler::new(None, Some(1024), Some(LogLevelFilter::Debug));
+    let buffer = hdlr.buffer();
+    for _ in 0..100 {
+        hdlr.handle(&create_record("Test - RingHandler - bytes")).unwrap();
+    }
+    assert!(buffer.bytes() <= 1024);
+    assert!(!buffer.is_empty() && buffer.len() < 100);
+}
+
+#[test]
+fn test_queue_handler() {
+    use handlers::queue::{Overflow, QueueHandler};
+
+    let ring = RingHandler::new(None, None, Some(LogLevelFilter::Debug));
+    let buffer = ring.buffer();
+    {
+        let mut hdlr = QueueHandler::new(vec![Handler::from(ring)], 2, Overflow::Block, Some(LogLevelFilter::Info));
+        for _ in 0..100 {
+            hdlr.handle(&create_record("Test - QueueHandler - block")).unwrap();
+        }
+        hdlr.handle(&create_leveled_record(LogLevel::Debug, "ignored")).unwrap();
+        assert_eq!(hdlr.stats().dropped(), 0);
+    }
+    assert_eq!(buffer.len(), 100);
+}
+
+#[test]
+fn test_queue_handler_overflow() {
+    use handlers::queue::{Overflow, QueueHandler};
+
+    for overflow in &[Overflow::DropNewest, Overflow::DropOldest, Overflow::DropBelow(LogLevel::Warn)] {
+        let ring = RingHandler::new(None, None, Some(LogLevelFilter::Debug));
+        let buffer = ring.buffer();
+        let stats = {
+            let mut hdlr = QueueHandler::new(vec![Handler::from(ring)], 1, *overflow, Some(LogLevelFilter::Info));
+            for _ in 0..1000 {
+                hdlr.handle(&create_record("Test - QueueHandler - overflow")).unwrap();
+            }
+            hdlr.stats()
+        };
+        assert_eq!(stats.pending(), 0);
+        assert_eq!(buffer.len() as u64 + stats.dropped(), 1000);
+    }
+}
+
+#[test]
+fn test_channel_handler() {
+    use handlers::channel::ChannelHandler;
+    use std::sync::mpsc;
+    use std::thread;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut hdlr = ChannelHandler::new(sender, Some(LogLevelFilter::Info));
+    thread::spawn(move || {
+        hdlr.handle(&create_record("Test - ChannelHandler - channel")).unwrap();
+        hdlr.handle(&create_leveled_record(LogLevel::Debug, "ignored")).unwrap();
+    }).join().unwrap();
+
+    let records: Vec<_> = receiver.iter().collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].msg, "Test - ChannelHandler - channel");
+    assert_eq!(records[0].file, "src/tests.rs");
+}
+
+#[test]
+fn test_callback_handler() {
+    use handlers::channel::ChannelHandler;
+
+    let msgs = Arc::new(Mutex::new(vec![]));
+    let shared = msgs.clone();
+    let mut hdlr = ChannelHandler::with_callback(move |record| shared.lock().unwrap().push(record.msg), Some(LogLevelFilter::Info));
+    hdlr.handle(&create_record("Test - ChannelHandler - callback")).unwrap();
+    assert_eq!(*msgs.lock().unwrap(), vec!["Test - ChannelHandler - callback"]);
+}
+
+#[test]
+fn test_custom_handler() {
+    use handlers::custom::CustomHandler;
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountHandler {
+        level: LogLevelFilter,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl Handle for CountHandler {
+        fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+            if self.level >= record.level() {
+                self.emit(record)
+            } else {
+                Ok(())
+            }
+        }
+        fn emit(&mut self, _record: &ExtendedLogRecord) -> io::Result<()> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let hdlr: Box<dyn Handle + Send> = Box::new(CountHandler { level: LogLevelFilter::Warn, count: count.clone() });
+    let mut hdlr = Handler::from(hdlr);
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - CustomHandler - error")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "Test - CustomHandler - info")).unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // The wrapper applies its own level and filters before the ones of the user defined handler.
+    let count = Arc::new(AtomicUsize::new(0));
+    let inner = CountHandler { level: LogLevelFilter::Trace, count: count.clone() };
+    let mut hdlr = CustomHandler::new(Box::new(inner), Some(LogLevelFilter::Warn));
+    hdlr.filters.push(|record| !record.msg.ends_with("filtered"));
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - CustomHandler - error")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - CustomHandler - filtered")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "Test - CustomHandler - info")).unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_writer_handler() {
+    use handlers::streams::writer::WriterHandler;
+
+    let output = Arc::new(Mutex::new(vec![]));
+    let mut hdlr = WriterHandler::new(Box::new(SharedBuffer(output.clone())), Some(LogLevelFilter::Info), Some(custom_formatter));
+    hdlr.handle(&create_record("Test - WriterHandler - custom_formatter")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "ignored")).unwrap();
+
+    let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+    assert!(output.ends_with(" - INFO - Test - WriterHandler - custom_formatter\n"));
+    assert_eq!(output.lines().count(), 1);
+}
+
+#[test]
+fn test_routing_handler() {
+    use handlers::routing::{Route, RoutingHandler, Rule};
+
+    fn is_retry(record: &ExtendedLogRecord) -> bool {
+        record.msg.contains("retry")
+    }
+
+    let (factory, factory_buffer) = recorder();
+    let (errors, errors_buffer) = recorder();
+    let (retries, retries_buffer) = recorder();
+    let (fallback, fallback_buffer) = recorder();
+    let buffers = [factory_buffer, errors_buffer, retries_buffer, fallback_buffer];
+
+    let mut error_route = Route::new(Rule::Levels(LogLevel::Error, LogLevel::Warn), vec![Handler::from(errors)]);
+    error_route.last = false;
+    let mut hdlr = RoutingHandler::new(
+        vec![
+            error_route,
+            Route::new(
+                Rule::All(vec![Rule::Target(String::from("TestFactory")), Rule::Module(String::from("log_tools"))]),
+                vec![Handler::from(factory)],
+            ),
+            Route::new(Rule::Predicate(is_retry), vec![Handler::from(retries)]),
+        ],
+        vec![Handler::from(fallback)],
+        Some(LogLevelFilter::Debug),
+    );
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "error")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Trace, "ignored")).unwrap();
+    hdlr.routes.remove(1);
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "retry")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Info, "other")).unwrap();
+
+    let counts: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    assert_eq!(counts, vec![2, 1, 1, 1]);
+}
+
+#[test]
+fn test_failover_handler() {
+    use handlers::failover::FailoverHandler;
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    struct FlakyHandler {
+        down: Arc<AtomicBool>,
+        ring: RingHandler,
+    }
+
+    impl Handle for FlakyHandler {
+        fn handle(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+            self.emit(record)
+        }
+        fn emit(&mut self, record: &ExtendedLogRecord) -> io::Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "down"));
+            }
+            self.ring.handle(record)
+        }
+    }
+
+    let down = Arc::new(AtomicBool::new(false));
+    let (ring, primary) = recorder();
+    let flaky: Box<dyn Handle + Send> = Box::new(FlakyHandler { down: down.clone(), ring });
+    let (ring, secondary) = recorder();
+
+    let mut hdlr = FailoverHandler::new(Handler::from(flaky), Handler::from(ring), Some(LogLevelFilter::Info));
+    hdlr.probe_interval = Duration::from_secs(3600);
+    hdlr.handle(&create_record("first")).unwrap();
+    down.store(true, Ordering::SeqCst);
+    hdlr.handle(&create_record("second")).unwrap();
+    assert!(hdlr.failed_over());
+    down.store(false, Ordering::SeqCst);
+    hdlr.handle(&create_record("third")).unwrap();
+    hdlr.probe_interval = Duration::from_secs(0);
+    hdlr.handle(&create_record("fourth")).unwrap();
+    hdlr.handle(&create_record("fifth")).unwrap();
+    assert!(!hdlr.failed_over());
+    assert_eq!(hdlr.switches(), 2);
+
+    let msgs = messages(&primary);
+    assert_eq!(msgs[0], "first");
+    assert!(msgs[1] == "fourth" && msgs[3] == "fifth");
+    assert!(msgs[2].starts_with("primary handler recovered"));
+    let msgs = messages(&secondary);
+    assert!(msgs[0].starts_with("primary handler failed (down)"));
+    assert_eq!(&msgs[1..], &["second", "third"]);
+}
+
+#[test]
+fn test_failover_handler_pipe() {
+    use handlers::failover::FailoverHandler;
+    use handlers::pipe::PipeHandler;
+    use std::time::Duration;
+
+    let mut pipe = PipeHandler::new("/nonexistent/command", &[], Some(LogLevelFilter::Info), None);
+    pipe.capacity = 0;
+    let (ring, secondary) = recorder();
+
+    let mut hdlr = FailoverHandler::new(Handler::from(pipe), Handler::from(ring), Some(LogLevelFilter::Info));
+    hdlr.probe_interval = Duration::from_secs(0);
+    hdlr.handle(&create_record("first")).unwrap();
+    hdlr.handle(&create_record("second")).unwrap();
+    hdlr.handle(&create_record("third")).unwrap();
+
+    let msgs = messages(&secondary);
+    assert!(msgs[0].starts_with("primary handler failed"));
+    assert_eq!(&msgs[1..], &["first", "second", "third"]);
+}
+
+#[test]
+fn test_tcp_handler_reconnect() {
+    use handlers::failover::FailoverHandler;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (ring, secondary) = recorder();
+    let tcp = TCPHandler::new(&address, Some(LogLevelFilter::Info), Some(custom_formatter));
+
+    let mut hdlr = FailoverHandler::new(Handler::from(tcp), Handler::from(ring), Some(LogLevelFilter::Info));
+    hdlr.probe_interval = Duration::from_secs(0);
+    hdlr.handle(&create_record("Test - TCPHandler - first")).unwrap();
+    {
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert!(line.ends_with("Test - TCPHandler - first\n"));
+    }
+
+    // The server is down: the record goes to the secondary handler.
+    drop(listener);
+    hdlr.handle(&create_record("Test - TCPHandler - second")).unwrap();
+    assert!(hdlr.failed_over());
+
+    // The server is back: the handler reconnects and the failover switches back.
+    let listener = TcpListener::bind(address.as_str()).unwrap();
+    hdlr.handle(&create_record("Test - TCPHandler - third")).unwrap();
+    assert!(!hdlr.failed_over());
+    let (stream, _) = listener.accept().unwrap();
+    let lines: Vec<String> = BufReader::new(stream).lines().take(2).map(|line| line.unwrap()).collect();
+    assert!(lines[0].ends_with("Test - TCPHandler - third"));
+    assert!(lines[1].contains("primary handler recovered"));
+
+    let msgs = messages(&secondary);
+    assert_eq!(&msgs[1..], &["Test - TCPHandler - second"]);
+}
+
+#[test]
+fn test_stream_handler_error() {
+    use handlers::streams::writer::WriterHandler;
+
+    struct BrokenWriter;
+
+    impl Write for BrokenWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut hdlr = WriterHandler::new(Box::new(BrokenWriter), Some(LogLevelFilter::Info), None);
+    assert_eq!(hdlr.handle(&create_record("Test - StreamHandler - error")).unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn test_capture_handler() {
+    use handlers::capture::{Capture, CaptureHandler};
+    use std::thread;
+
+    let mut hdlr = CaptureHandler::new(Some(LogLevelFilter::Debug));
+    hdlr.handle(&create_record("Test - CaptureHandler - ignored")).unwrap();
+
+    let capture = Capture::start();
+    hdlr.handle(&create_leveled_record(LogLevel::Warn, "query timeout after 30s")).unwrap();
+    hdlr.handle(&create_record("Test - CaptureHandler - info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Trace, "Test - CaptureHandler - trace")).unwrap();
+
+    assert_logged!(level = Warn, target = "Test", msg_contains = "timeout");
+    assert_logged!(msg = "Test - CaptureHandler - info");
+    assert_not_logged!(level = Error);
+    assert_not_logged!(level = Info, msg_contains = "timeout");
+    assert_eq!(capture.count(LogLevel::Warn), 1);
+    assert_eq!(capture.counts()[..3], [(LogLevel::Error, 0), (LogLevel::Warn, 1), (LogLevel::Info, 1)]);
+    assert_eq!(capture.records().len(), 2);
+
+    // Records logged by another thread are not captured by this test.
+    thread::spawn(move || {
+        hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - CaptureHandler - thread")).unwrap();
+    }).join().unwrap();
+    assert_not_logged!(level = Error);
+
+    capture.clear();
+    assert!(capture.records().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "no record matching")]
+fn test_capture_assert_logged_fails() {
+    use handlers::capture::Capture;
+
+    let _capture = Capture::start();
+    assert_logged!(level = Error, msg_contains = "never logged");
+}
+
+#[test]
+fn test_metrics_handler() {
+    use handlers::metrics::MetricsHandler;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let mut hdlr = MetricsHandler::new(Some(LogLevelFilter::Info));
+    let metrics = hdlr.metrics();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - MetricsHandler - error")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - MetricsHandler - error")).unwrap();
+    hdlr.handle(&create_record("Test - MetricsHandler - info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - MetricsHandler - debug")).unwrap();
+
+    assert_eq!(metrics.count("ERROR", "TestFactory"), 2);
+    assert_eq!(metrics.count("DEBUG", "TestFactory"), 0);
+    assert!(metrics.render().contains("log_records_total{level=\"ERROR\",target=\"TestFactory\"} 2\n"));
+
+    hdlr.per_module = true;
+    hdlr.handle(&create_record("Test - MetricsHandler - info")).unwrap();
+    assert_eq!(metrics.count("INFO", "TestFactory"), 2);
+    assert!(metrics.render().contains("log_records_total{level=\"INFO\",target=\"TestFactory\",module=\"log_tools::tests\"} 1\n"));
+
+    let address = metrics.serve("127.0.0.1:0").unwrap();
+    // An idle client does not hold back the others.
+    let _idle = TcpStream::connect(address).unwrap();
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(&metrics.render()));
+}
+
+#[test]
+fn test_dedup_handler() {
+    use handlers::dedup::{DedupHandler, DedupKey};
+    use std::thread;
+    use std::time::Duration;
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = DedupHandler::new(Handler::from(ring), DedupKey::Message, None, Some(LogLevelFilter::Info));
+    for _ in 0..3 {
+        hdlr.handle(&create_record("connection refused, retrying")).unwrap();
+    }
+    hdlr.handle(&create_record("connected")).unwrap();
+    hdlr.handle(&create_record("connection refused, retrying")).unwrap();
+    hdlr.handle(&create_record("connection refused, retrying")).unwrap();
+    drop(hdlr);
+    let msgs = messages(&buffer);
+    assert_eq!(msgs, [
+        "connection refused, retrying",
+        "message repeated 2 times: connection refused, retrying",
+        "connected",
+        "connection refused, retrying",
+        "message repeated 1 time: connection refused, retrying",
+    ]);
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = DedupHandler::new(Handler::from(ring), DedupKey::Location, Some(Duration::from_millis(100)), Some(LogLevelFilter::Info));
+    let attempt = |msg: &str| {
+        let mut record = create_record("attempt");
+        record.msg = String::from(msg);
+        record
+    };
+    for msg in &["attempt 1", "attempt 2"] {
+        hdlr.handle(&attempt(msg)).unwrap();
+        let mut other = create_record("other");
+        other.line += 1;
+        hdlr.handle(&other).unwrap();
+    }
+    assert_eq!(buffer.len(), 2);
+    thread::sleep(Duration::from_millis(150));
+    hdlr.handle(&attempt("attempt 3")).unwrap();
+    let msgs = messages(&buffer);
+    assert_eq!(&msgs[2..], ["message repeated 1 time: attempt 2", "message repeated 1 time: other", "attempt 3"]);
+}
+
+#[test]
+fn test_ratelimit_handler() {
+    use handlers::ratelimit::{RateLimitHandler, RateLimitKey};
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    thread_local! {
+        static NOW: Cell<Instant> = Cell::new(Instant::now());
+    }
+
+    fn clock() -> Instant {
+        NOW.with(|now| now.get())
+    }
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = RateLimitHandler::new(Handler::from(ring), RateLimitKey::Level, 3, 20.0, Some(LogLevelFilter::Info));
+    hdlr.notice_interval = Duration::from_millis(100);
+    hdlr.clock = clock;
+    for _ in 0..10 {
+        hdlr.handle(&create_record("Test - RateLimitHandler - flood")).unwrap();
+    }
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - RateLimitHandler - error")).unwrap();
+    assert_eq!(buffer.len(), 4);
+    assert_eq!(hdlr.suppressed(), 7);
+
+    // 50ms refill a token, the notices are not due yet.
+    NOW.with(|now| now.set(now.get() + Duration::from_millis(50)));
+    hdlr.handle(&create_record("Test - RateLimitHandler - refilled")).unwrap();
+    hdlr.handle(&create_record("Test - RateLimitHandler - flood")).unwrap();
+    assert_eq!(buffer.len(), 5);
+    assert_eq!(hdlr.suppressed(), 8);
+
+    NOW.with(|now| now.set(now.get() + Duration::from_millis(100)));
+    hdlr.handle(&create_record("Test - RateLimitHandler - noticed")).unwrap();
+    let msgs = messages(&buffer);
+    assert_eq!(&msgs[4..], [
+        "Test - RateLimitHandler - refilled",
+        "suppressed 8 records from INFO",
+        "Test - RateLimitHandler - noticed",
+    ]);
+    assert_eq!(hdlr.suppressed(), 0);
+}
+
+#[test]
+fn test_sampling_handler() {
+    use handlers::sampling::{bucket, SamplingHandler};
+    use formatter::Pattern;
+
+    fn request_id(record: &ExtendedLogRecord) -> String {
+        record.msg.split_whitespace().next().unwrap_or("").to_string()
+    }
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = SamplingHandler::new(Handler::from(ring), Some(LogLevelFilter::Debug));
+    hdlr.set_rate(LogLevel::Info, 0.1);
+    hdlr.set_rate(LogLevel::Debug, 0.0);
+    hdlr.set_seed(42);
+    for _ in 0..1000 {
+        hdlr.handle(&create_record("Test - SamplingHandler - info")).unwrap();
+        hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - SamplingHandler - debug")).unwrap();
+    }
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - SamplingHandler - error")).unwrap();
+    let records = buffer.snapshot();
+    assert_eq!(records.len(), 113);
+    assert!(records.iter().all(|record| record.level != "DEBUG"));
+    assert_eq!(records[0].msg, "Test - SamplingHandler - info");
+    assert_eq!(records[0].sample_rate, Some(0.1));
+    assert!(logfmt(&records[0].as_record()).ends_with(" sample_rate=0.1\n"));
+    let pattern = Pattern::new("{msg}{? [sample_rate={sample_rate}]}").unwrap();
+    assert_eq!(pattern.format(&records[0].as_record()), "Test - SamplingHandler - info [sample_rate=0.1]");
+    assert_eq!(records.last().unwrap().msg, "Test - SamplingHandler - error");
+    assert_eq!(records.last().unwrap().sample_rate, None);
+    assert_eq!(pattern.format(&records.last().unwrap().as_record()), "Test - SamplingHandler - error");
+
+    // The same seed picks the same records.
+    buffer.clear();
+    hdlr.set_seed(42);
+    for _ in 0..1000 {
+        hdlr.handle(&create_record("Test - SamplingHandler - info")).unwrap();
+    }
+    assert_eq!(buffer.len(), records.len() - 1);
+
+    buffer.clear();
+    hdlr.key = Some(request_id);
+    let msgs: Vec<String> = (0..100).map(|idx| format!("req-{} started", idx)).collect();
+    for msg in msgs.iter() {
+        let mut record = create_record("");
+        record.msg = msg.clone();
+        hdlr.handle(&record).unwrap();
+        record.msg = msg.replace("started", "done");
+        hdlr.handle(&record).unwrap();
+    }
+    let records = buffer.snapshot();
+    assert!(!records.is_empty() && records.len() % 2 == 0);
+    for pair in records.chunks(2) {
+        assert_eq!(request_id(&pair[0].as_record()), request_id(&pair[1].as_record()));
+    }
+
+    // The buckets of the keys don't depend on the Rust release.
+    assert_eq!(bucket("a"), 0xaf63dc4c8601ec8c % 1000000);
+    assert_eq!(bucket("req-42"), 298888);
+    assert!(!records.iter().any(|record| record.msg.starts_with("req-42 ")));
+}
+
+#[test]
+fn test_digest_handler() {
+    use handlers::digest::DigestHandler;
+    use std::time::Duration;
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = DigestHandler::new(Handler::from(ring), Duration::from_secs(3600), Some(LogLevelFilter::Info));
+    for msg in &["query 1 took 120ms", "query 2 took 85ms", "query 3 took 97ms"] {
+        let mut record = create_leveled_record(LogLevel::Warn, "");
+        record.msg = msg.to_string();
+        hdlr.handle(&record).unwrap();
+    }
+    hdlr.handle(&create_record("Test - DigestHandler - info")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - DigestHandler - debug")).unwrap();
+    assert_eq!(buffer.len(), 0);
+
+    hdlr.flush().unwrap();
+    let records = buffer.snapshot();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].level, "WARN");
+    let lines: Vec<&str> = records[0].msg.lines().collect();
+    assert_eq!(lines[0], "digest of 4 records over 0s:");
+    assert!(lines[1].starts_with("  3 x WARN TestFactory \"query * took *\" ("));
+    assert!(lines[1].ends_with("), e.g. \"query 1 took 120ms\""));
+    assert!(lines[2].starts_with("  1 x INFO TestFactory \"Test - DigestHandler - info\""));
+
+    drop(hdlr);
+    assert_eq!(buffer.len(), 1);
+}
+
+#[test]
+fn test_alert_handler() {
+    use handlers::alert::{Alert, AlertHandler};
+    use std::thread;
+    use std::time::Duration;
+
+    let alerts = Arc::new(Mutex::new(vec![]));
+    let raised = alerts.clone();
+    let mut hdlr = AlertHandler::with_callback(
+        move |alert: &Alert| raised.lock().unwrap().push(alert.clone()),
+        3,
+        Duration::from_millis(100),
+        Some(LogLevelFilter::Error),
+    );
+    for _ in 0..5 {
+        hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - AlertHandler - error")).unwrap();
+        hdlr.handle(&create_record("Test - AlertHandler - info")).unwrap();
+    }
+    assert_eq!(*alerts.lock().unwrap(), [Alert { target: String::from("TestFactory"), count: 4, firing: true }]);
+    assert_eq!(hdlr.firing(), ["TestFactory"]);
+
+    thread::sleep(Duration::from_millis(150));
+    hdlr.handle(&create_record("Test - AlertHandler - info")).unwrap();
+    assert_eq!(alerts.lock().unwrap()[1], Alert { target: String::from("TestFactory"), count: 0, firing: false });
+    assert!(hdlr.firing().is_empty());
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = AlertHandler::new(Handler::from(ring), 1, Duration::from_millis(100), Some(LogLevelFilter::Error));
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - AlertHandler - error")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - AlertHandler - error")).unwrap();
+    thread::sleep(Duration::from_millis(150));
+    hdlr.check().unwrap();
+    let records = buffer.snapshot();
+    assert_eq!(records[0].level, "ERROR");
+    assert_eq!(records[0].msg, "alert: 2 records from TestFactory in 0s, above the threshold of 1");
+    assert_eq!(records[1].level, "INFO");
+    assert!(records[1].msg.starts_with("recovered: 0 records from TestFactory"));
+}
+
+#[test]
+fn test_scope_handler() {
+    use handlers::HANDLERS;
+    use handlers::scope::{RequestScope, ScopeHandler};
+
+    let (ring, buffer) = recorder();
+    let mut hdlr = ScopeHandler::new(Handler::from(ring), LogLevelFilter::Info, Some(LogLevelFilter::Debug));
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - outside")).unwrap();
+    hdlr.handle(&create_record("Test - ScopeHandler - outside")).unwrap();
+    assert_eq!(buffer.len(), 1);
+
+    let scope = RequestScope::open();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - succeeded")).unwrap();
+    hdlr.handle(&create_record("Test - ScopeHandler - succeeded")).unwrap();
+    assert_eq!(hdlr.len(), 2);
+    assert_eq!(buffer.len(), 1);
+    // Closing a scope doesn't need the logger.
+    {
+        let _handlers = HANDLERS.lock().unwrap();
+        drop(scope);
+    }
+    assert_eq!(buffer.len(), 2);
+
+    // The records of a nested scope which succeeded follow the outer scope.
+    let scope = RequestScope::open();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - failed")).unwrap();
+    {
+        let _inner = RequestScope::open();
+        hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - inner")).unwrap();
+    }
+    assert_eq!(hdlr.len(), 2);
+    assert!(!scope.failed());
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - ScopeHandler - failed")).unwrap();
+    assert!(scope.failed());
+    scope.close().unwrap();
+    let msgs: Vec<(String, String)> = buffer.snapshot().into_iter().map(|record| (record.level, record.msg)).collect();
+    assert_eq!(&msgs[2..], [
+        (String::from("DEBUG"), String::from("Test - ScopeHandler - failed")),
+        (String::from("DEBUG"), String::from("Test - ScopeHandler - inner")),
+        (String::from("ERROR"), String::from("Test - ScopeHandler - failed")),
+    ]);
+
+    // A nested scope which failed is sent on its own.
+    let scope = RequestScope::open();
+    {
+        let inner = RequestScope::open();
+        hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - marked")).unwrap();
+        inner.fail();
+    }
+    assert_eq!(buffer.snapshot().last().unwrap().msg, "Test - ScopeHandler - marked");
+    assert!(!scope.failed());
+    drop(scope);
+    assert!(hdlr.is_empty());
+}
+
+#[test]
+fn test_scope_handler_queue() {
+    use handlers::queue::{Overflow, QueueHandler};
+    use handlers::scope::{RequestScope, ScopeHandler};
+
+    let (ring, buffer) = recorder();
+    let scoped = ScopeHandler::new(Handler::from(ring), LogLevelFilter::Info, Some(LogLevelFilter::Debug));
+    let mut hdlr = QueueHandler::new(vec![Handler::from(scoped)], 100, Overflow::Block, Some(LogLevelFilter::Debug));
+
+    let scope = RequestScope::open();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - succeeded")).unwrap();
+    drop(scope);
+    let scope = RequestScope::open();
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - ScopeHandler - failed")).unwrap();
+    scope.fail();
+    drop(scope);
+    hdlr.handle(&create_record("Test - ScopeHandler - outside")).unwrap();
+    drop(hdlr);
+
+    let msgs = messages(&buffer);
+    assert_eq!(msgs, ["Test - ScopeHandler - failed", "Test - ScopeHandler - outside"]);
+}
+
+#[test]
+fn test_statsd_handler() {
+    use handlers::statsd::StatsdHandler;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    agent.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let address = agent.local_addr().unwrap().to_string();
+    let receive = || {
+        let mut buf = [0; 512];
+        let (size, _) = agent.recv_from(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..size]).into_owned()
+    };
+
+    assert!(StatsdHandler::new("127.0.0.1", "logs.count", Some(LogLevelFilter::Info)).is_err());
+    let mut hdlr = StatsdHandler::new(&address, "logs.count", Some(LogLevelFilter::Info)).unwrap();
+    hdlr.tags.push(String::from("env:test,prod"));
+    hdlr.handle(&create_leveled_record(LogLevel::Debug, "Test - StatsdHandler - debug")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "Test - StatsdHandler - error")).unwrap();
+    assert_eq!(receive(), "logs.count:1|c|#level:error,target:TestFactory,env:test_prod");
+
+    hdlr.dogstatsd = false;
+    hdlr.handle(&create_record("Test - StatsdHandler - info")).unwrap();
+    assert_eq!(receive(), "logs.count.info:1|c");
+}
+
+#[test]
+fn test_extract_handler() {
+    use handlers::extract::{ExtractHandler, MetricKind, MetricRule, RuleError};
+
+    let mut latency = MetricRule::new("query_duration_ms", r"(?P<table>\w+) query took (\d+)ms", MetricKind::Histogram(vec![10.0, 100.0])).unwrap();
+    latency.target = Some(String::from("Test"));
+    let mut errors = MetricRule::new("query_errors_total", r"query failed", MetricKind::Counter).unwrap();
+    errors.level = LogLevelFilter::Error;
+    let pool = MetricRule::new("pool_size", r"pool size: (?P<value>\d+)", MetricKind::Gauge).unwrap();
+    assert!(MetricRule::new("pool_size", r"pool size: \d+", MetricKind::Gauge).is_err());
+    assert!(MetricRule::new("pool-size", r"pool size: (\d+)", MetricKind::Gauge).is_err());
+    assert!(MetricRule::new("pool_size", r"(?P<__name__>\w+) size: (\d+)", MetricKind::Gauge).is_err());
+    assert!(MetricRule::new("query_duration_ms", r"(?P<le>\w+) query took (\d+)ms", MetricKind::Histogram(vec![10.0])).is_err());
+    assert!(MetricRule::new("query_level_total", r"(?P<le>\w+) query", MetricKind::Counter).is_ok());
+    assert_eq!(MetricRule::new("query_duration_ms", r"took (\d+)ms", MetricKind::Histogram(vec![100.0, 10.0])).err(), Some(RuleError::Buckets(vec![100.0, 10.0])));
+    assert!(MetricRule::new("query_duration_ms", r"took (\d+)ms", MetricKind::Histogram(vec![10.0, f64::NAN])).is_err());
+    assert!(MetricRule::new("query_duration_ms", r"took (\d+)ms", MetricKind::Histogram(vec![])).is_err());
+    assert_eq!(MetricRule::new("pool_size", r"pool size: \d+", MetricKind::Gauge).err(), Some(RuleError::NoValue));
+    match MetricRule::new("pool_size", r"pool size: (\d+", MetricKind::Gauge) {
+        Err(RuleError::Regex(_)) => {}
+        _ => panic!("the regex should not compile"),
+    }
+
+    let retries = MetricRule::new("retries_total", r"retried (-?\d+) times", MetricKind::Counter).unwrap();
+    let mut hdlr = ExtractHandler::new(vec![latency, errors, pool, retries], Some(LogLevelFilter::Info));
+    let metrics = hdlr.metrics();
+    for msg in &["users query took 5ms", "users query took 50ms", "users query took 500ms", "orders query took 8ms", "pool size: 4", "pool size: 3"] {
+        let mut record = create_record("");
+        record.msg = msg.to_string();
+        hdlr.handle(&record).unwrap();
+    }
+    hdlr.handle(&create_record("query failed")).unwrap();
+    hdlr.handle(&create_leveled_record(LogLevel::Error, "query failed")).unwrap();
+    hdlr.handle(&create_record("retried 3 times")).unwrap();
+    hdlr.handle(&create_record("retried -2 times")).unwrap();
+
+    assert_eq!(metrics.histogram("query_duration_ms", &[("table", "users")]), Some((vec![1, 2, 3], 555.0, 3)));
+    assert_eq!(metrics.get("query_errors_total", &[]), Some(1.0));
+    assert_eq!(metrics.get("retries_total", &[]), Some(3.0));
+    assert_eq!(metrics.get("pool_size", &[]), Some(3.0));
+    assert_eq!(metrics.get("pool_size", &[("table", "users")]), None);
+
+    let text = metrics.render();
+    assert!(text.contains("# HELP query_duration_ms Extracted from the log records matching (?P<table>\\\\w+) query took (\\\\d+)ms.\n"));
+    assert!(text.contains("# TYPE query_duration_ms histogram\n"));
+    assert!(text.contains("query_duration_ms_bucket{table=\"orders\",le=\"10\"} 1\n"));
+    assert!(text.contains("query_duration_ms_bucket{table=\"users\",le=\"+Inf\"} 3\n"));
+    assert!(text.contains("query_duration_ms_sum{table=\"users\"} 555\n"));
+    assert!(text.contains("query_errors_total 1\n"));
+    assert!(text.contains("# TYPE pool_size gauge\npool_size 3\n"));
+}
+
+#[test]
+fn test_pattern_formatter() {
+    use formatter::Pattern;
+
+    let mut record = create_record("Test - Pattern");
+    record.target = String::from("log_tools::handlers::streams");
+    record.line = 42;
+
+    let pattern = Pattern::new("{date} [{level:<5}] {target:.20}:{line} - {msg}").unwrap();
+    assert_eq!(pattern.format(&record), format!("{} [INFO ] log_tools::handlers::42 - Test - Pattern", record.date));
+
+    let pattern: Pattern = "{level:>7}|{levelno:*^5}|{{{msg:.4}}}{? ({module})}".parse().unwrap();
+    assert_eq!(pattern.format(&record), "   INFO|**3**|{Test} (log_tools::tests)");
+    record.module = "";
+    assert_eq!(pattern.format(&record), "   INFO|**3**|{Test}");
+
+    assert_eq!(Pattern::new("{lvl}").unwrap_err().reason, "unknown field 'lvl'");
+    assert_eq!(Pattern::new("{msg").unwrap_err().reason, "unclosed field");
+    assert_eq!(Pattern::new("{? {msg}").unwrap_err().reason, "unclosed optional section");
+    assert_eq!(Pattern::new("msg}").unwrap_err().position, 3);
+    assert!(Pattern::new("{line:5x}").is_err());
+}
+
+#[test]
+fn test_logfmt_formatter() {
+    let mut record = create_record("Test - logfmt");
+    record.target = String::from("db");
+    assert_eq!(
+        logfmt(&record),
+        format!("ts={} level=info target=db msg=\"Test - logfmt\" file=src/tests.rs line={}\n", record.date, record.line)
+    );
+
+    record.msg = String::from("key=\"value\"\npath=C:\\tmp\t\u{1b}[0m");
+    record.target = String::new();
+    let line = logfmt(&record);
+    assert!(line.contains(" target=\"\" "));
+    assert!(line.contains(" msg=\"key=\\\"value\\\"\\npath=C:\\\\tmp\\t\\u001b[0m\" "));
+    assert_eq!(line.lines().count(), 1);
+}
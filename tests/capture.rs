@@ -0,0 +1,43 @@
+//!
+//! Capture of the records logged through the `log` macros.
+//!
+//! It runs in its own process: the logger installed by `capture::init` would otherwise conflict
+//! with the unit tests installing theirs.
+//!
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate log_tools;
+
+use log::{LogLevel, LogLevelFilter};
+use log_tools::handlers::capture::{self, Capture};
+use std::thread;
+
+#[test]
+fn test_capture_logger() {
+    capture::init(LogLevelFilter::Debug).unwrap();
+    let capture = Capture::start();
+    warn!(target: "db", "query timeout after {}s", 30);
+    debug!("cache miss");
+    trace!("ignored");
+
+    assert_logged!(level = Warn, target = "db", msg = "query timeout after 30s");
+    assert_logged!(level = Debug, msg_contains = "miss");
+    assert_not_logged!(level = Trace);
+    assert_eq!(capture.count(LogLevel::Warn), 1);
+
+    // Records logged by another thread are not captured by this test.
+    thread::spawn(|| error!("from another thread")).join().unwrap();
+    assert_not_logged!(level = Error);
+}
+
+#[test]
+fn test_capture_init_twice() {
+    capture::init(LogLevelFilter::Debug).unwrap();
+    capture::init(LogLevelFilter::Debug).unwrap();
+    let _capture = Capture::start();
+    info!("logged once");
+
+    assert_eq!(capture::records().len(), 1);
+}